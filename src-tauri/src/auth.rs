@@ -0,0 +1,83 @@
+use ssh2::Session;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::storage::AuthMethod;
+
+/// Выполняет аутентификацию выбранным для сервера способом.
+pub fn authenticate(sess: &Session, username: &str, password: &str, auth_method: &AuthMethod) -> Result<(), AppError> {
+    match auth_method {
+        AuthMethod::Password => sess
+            .userauth_password(username, password)
+            .map_err(|e| AppError::ssh(Msg::AuthFailed.text(), &e))?,
+        AuthMethod::PublicKey { private_key_path, passphrase } => {
+            authenticate_with_key(sess, username, private_key_path, passphrase.as_deref())?
+        }
+        AuthMethod::Agent => authenticate_with_agent(sess, username)?,
+    }
+
+    if !sess.authenticated() {
+        return Err(AppError::new(ErrorCode::AuthFailed, Msg::AuthRejected.text()));
+    }
+
+    Ok(())
+}
+
+fn authenticate_with_key(sess: &Session, username: &str, private_key_path: &str, passphrase: Option<&str>) -> Result<(), AppError> {
+    let private_key = expand_home(private_key_path);
+
+    if !private_key.exists() {
+        return Err(AppError::not_found(Msg::KeyFileNotFound.format(&[&private_key.display()])));
+    }
+
+    // Публичный ключ рядом с приватным нужен libssh2 без OpenSSL; с OpenSSL он вычисляется сам
+    let public_key = PathBuf::from(format!("{}.pub", private_key.display()));
+    let public_key = if public_key.exists() { Some(public_key.as_path()) } else { None };
+
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+
+    sess.userauth_pubkey_file(username, public_key, &private_key, passphrase)
+        .map_err(|e| AppError::ssh(&Msg::KeyAuthFailed.format(&[&private_key.display()]), &e))
+}
+
+fn authenticate_with_agent(sess: &Session, username: &str) -> Result<(), AppError> {
+    let mut agent = sess.agent()
+        .map_err(|e| AppError::ssh(Msg::AgentInitFailed.text(), &e))?;
+
+    agent.connect()
+        .map_err(|e| AppError::ssh(Msg::AgentConnectFailed.text(), &e))?;
+
+    agent.list_identities()
+        .map_err(|e| AppError::ssh(Msg::AgentKeysFailed.text(), &e))?;
+
+    let identities = agent.identities()
+        .map_err(|e| AppError::ssh(Msg::AgentKeysFailed.text(), &e))?;
+
+    if identities.is_empty() {
+        let _ = agent.disconnect();
+        return Err(AppError::new(ErrorCode::AuthFailed, Msg::AgentNoKeys.text()));
+    }
+
+    let authenticated = identities
+        .iter()
+        .any(|identity| agent.userauth(username, identity).is_ok());
+
+    let _ = agent.disconnect();
+
+    if authenticated {
+        Ok(())
+    } else {
+        Err(AppError::new(ErrorCode::AuthFailed, Msg::AgentKeysRejected.text()))
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Ok(home) = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+            return Path::new(&home).join(rest);
+        }
+    }
+
+    PathBuf::from(path)
+}
@@ -1,15 +1,18 @@
 use serde::{Deserialize, Serialize};
-use ssh2::Session;
+use ssh2::{OpenFlags, OpenType, Session, Sftp};
 use std::io::{Read, Write};
-use std::net::TcpStream;
-use tauri::command;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SshConnectionInfo {
-    pub username: String,
-    pub host: String,
-    pub password: String,
-}
+use std::path::Path;
+use tauri::{command, State};
+
+use crate::connection::{get_session, run_as_admin, run_with_sudo_fallback, SshConnectionInfo};
+use crate::error::{is_sftp_permission_denied, AppError};
+use crate::i18n::Msg;
+use crate::listdirectory::read_directory;
+use crate::local_transfer::partial_name;
+use crate::path_policy::{check_paths, PolicyOperation};
+use crate::remote_command::{run_command, RemoteCommand};
+use crate::session_pool::SessionPool;
+use crate::transfer_resume::{copy_resumable, forget, ResumableFile, SourceVersion};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileTransferRequest {
@@ -18,332 +21,372 @@ pub struct FileTransferRequest {
     pub file_path: String,
     pub is_folder: bool,
     pub destination_path: String,
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    /// Пользователь подтвердил запись в путь, защищенный политикой с подтверждением
+    #[serde(default)]
+    pub confirmed: bool,
 }
 
-fn create_ssh_session(connection_info: &SshConnectionInfo) -> Result<Session, String> {
-    let host_string = if connection_info.host.contains('@') {
-        connection_info.host.clone()
-    } else {
-        format!("{}@{}", connection_info.username, connection_info.host)
-    };
+/// Что делать, если файл назначения уже существует. Директории всегда объединяются,
+/// политика применяется к каждому файлу внутри них.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    Fail,
+}
 
-    let parts: Vec<&str> = host_string.split('@').collect();
-    
-    if parts.len() != 2 {
-        return Err("Неверный формат строки подключения".to_string());
+impl OverwritePolicy {
+    /// Возвращает `true`, если файл нужно копировать, и ошибку для политики `Fail`.
+    pub fn should_copy(self, exists: bool, path: &str) -> Result<bool, AppError> {
+        match (exists, self) {
+            (false, _) | (true, OverwritePolicy::Overwrite) => Ok(true),
+            (true, OverwritePolicy::Skip) => Ok(false),
+            (true, OverwritePolicy::Fail) => Err(AppError::conflict(Msg::FileExists.format(&[&path]))),
+        }
     }
-    
-    let username = parts[0];
-    let host = parts[1];
-
-    let tcp = TcpStream::connect(format!("{}:22", host))
-        .map_err(|e| format!("Ошибка подключения к серверу: {}", e))?;
+}
 
-    let mut sess = Session::new()
-        .map_err(|e| format!("Ошибка создания SSH-сессии: {}", e))?;
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
 
-    sess.set_tcp_stream(tcp);
-    sess.handshake()
-        .map_err(|e| format!("Ошибка при рукопожатии SSH: {}", e))?;
+/// Что делать с элементом папки при рекурсивном копировании.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WalkEntry {
+    Skip,
+    Folder,
+    File,
+}
 
-    sess.userauth_password(username, &connection_info.password)
-        .map_err(|e| format!("Ошибка аутентификации: {}", e))?;
+impl WalkEntry {
+    /// Ссылки на папки не обходятся, чтобы не зациклиться; ссылки на файлы копируются как
+    /// обычные файлы. Правило общее для копирования между серверами, загрузки и скачивания.
+    pub fn of(is_symlink: bool, is_folder: bool) -> Self {
+        match (is_symlink, is_folder) {
+            (true, true) => WalkEntry::Skip,
+            (false, true) => WalkEntry::Folder,
+            (_, false) => WalkEntry::File,
+        }
+    }
+}
 
-    Ok(sess)
+/// Снимок хода копирования, который получает `TransferObserver`.
+pub struct TransferStats<'a> {
+    pub copied: usize,
+    pub skipped: usize,
+    pub bytes: u64,
+    pub current_file: &'a str,
 }
 
-fn get_file_permissions(session: &Session, file_path: &str) -> Result<u32, String> {
-    let mut channel = session.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
+/// Получатель хода копирования. Ошибка из `progress` прерывает передачу — так отменяются фоновые задачи.
+pub trait TransferObserver: Send {
+    fn progress(&mut self, stats: &TransferStats) -> Result<(), AppError>;
 
-    let command = format!("stat -c '%a' '{}'", file_path.replace("'", "'\"'\"'"));
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
 
-    let mut output = String::new();
-    channel.read_to_string(&mut output)
-        .map_err(|e| format!("Ошибка чтения вывода: {}", e))?;
+/// Итог копирования: сколько файлов скопировано и сколько пропущено по политике перезаписи.
+/// Если задан наблюдатель, он получает каждое изменение счетчиков.
+#[derive(Default)]
+pub struct TransferSummary {
+    pub copied: usize,
+    pub skipped: usize,
+    pub bytes: u64,
+    current_file: String,
+    observer: Option<Box<dyn TransferObserver>>,
+}
 
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+impl TransferSummary {
+    pub fn observed(observer: Box<dyn TransferObserver>) -> Self {
+        TransferSummary {
+            observer: Some(observer),
+            ..Default::default()
+        }
+    }
 
-    let permissions = output.trim().parse::<u32>()
-        .map_err(|_| "Ошибка парсинга прав доступа".to_string())?;
+    pub fn start_file(&mut self, path: &str) -> Result<(), AppError> {
+        self.current_file = path.to_string();
+        self.notify()
+    }
 
-    Ok(permissions)
-}
+    pub fn add_bytes(&mut self, bytes: u64) -> Result<(), AppError> {
+        self.bytes += bytes;
+        self.notify()
+    }
 
-fn transfer_file_content(
-    source_session: &Session,
-    dest_session: &Session,
-    dest_connection: &SshConnectionInfo,
-    source_path: &str,
-    dest_path: &str,
-) -> Result<(), String> {
-    let source_sftp = source_session.sftp()
-        .map_err(|e| format!("Ошибка создания SFTP канала источника: {}", e))?;
+    pub fn file_copied(&mut self) -> Result<(), AppError> {
+        self.copied += 1;
+        self.notify()
+    }
 
-    let permissions = get_file_permissions(source_session, source_path).unwrap_or(0o644);
+    pub fn file_skipped(&mut self) -> Result<(), AppError> {
+        self.skipped += 1;
+        self.notify()
+    }
 
-    let mut source_file = source_sftp.open(&std::path::Path::new(source_path))
-        .map_err(|e| format!("Ошибка открытия исходного файла: {}", e))?;
+    pub fn is_cancelled(&self) -> bool {
+        self.observer.as_ref().is_some_and(|observer| observer.is_cancelled())
+    }
 
-    let temp_file = format!("/tmp/ssh_transfer_{}", std::process::id());
-    
-    let dest_sftp = dest_session.sftp()
-        .map_err(|e| format!("Ошибка создания SFTP канала получателя: {}", e))?;
-    
-    let mut temp_dest_file = dest_sftp.create(&std::path::Path::new(&temp_file))
-        .map_err(|e| format!("Ошибка создания временного файла: {}", e))?;
+    fn notify(&mut self) -> Result<(), AppError> {
+        match self.observer.as_mut() {
+            Some(observer) => observer.progress(&TransferStats {
+                copied: self.copied,
+                skipped: self.skipped,
+                bytes: self.bytes,
+                current_file: &self.current_file,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn describe(&self, copied_message: String) -> String {
+        if self.skipped == 0 {
+            copied_message
+        } else {
+            Msg::TransferSummary.format(&[&copied_message, &self.copied, &self.skipped])
+        }
+    }
+}
 
-    let mut buffer = vec![0u8; 8192];
+/// Копирует поток блоками, сообщая о каждом блоке в `summary` (и проверяя отмену).
+pub fn copy_with_progress(reader: &mut impl Read, writer: &mut impl Write, summary: &mut TransferSummary) -> Result<(), AppError> {
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
     loop {
-        let bytes_read = source_file.read(&mut buffer)
-            .map_err(|e| format!("Ошибка чтения из исходного файла: {}", e))?;
+        let bytes_read = reader.read(&mut buffer)
+            .map_err(|e| AppError::io(Msg::SourceReadFailed.text(), &e))?;
         
         if bytes_read == 0 {
-            break;
+            return Ok(());
         }
         
-        temp_dest_file.write_all(&buffer[..bytes_read])
-            .map_err(|e| format!("Ошибка записи во временный файл: {}", e))?;
+        writer.write_all(&buffer[..bytes_read])
+            .map_err(|e| AppError::io(Msg::DestinationWriteFailed.text(), &e))?;
+
+        summary.add_bytes(bytes_read as u64)?;
     }
+}
 
-    drop(temp_dest_file);
+fn get_file_permissions(session: &Session, file_path: &str) -> Result<u32, AppError> {
+    let output = run_command(session, &RemoteCommand::new("stat").arg("-c").arg("%a").arg(file_path))?;
 
-    let mut channel = dest_session.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
+    let permissions = u32::from_str_radix(output.stdout.trim(), 8)
+        .map_err(|e| AppError::other(Msg::PermissionsParseFailed.text(), e))?;
 
-    let escaped_dest_path = dest_path.replace("'", "'\"'\"'");
-    let escaped_temp_file = temp_file.replace("'", "'\"'\"'");
-    
-    let command = format!("cp '{}' '{}' && rm '{}'", escaped_temp_file, escaped_dest_path, escaped_temp_file);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
+    Ok(permissions)
+}
 
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
+/// Каталог в домашней папке пользователя для недокачанных файлов, которые нельзя положить
+/// рядом с файлом назначения. Доступен только владельцу, в отличие от общего /tmp.
+const STAGING_DIR: &str = ".ssh-connect-partial";
+
+fn open_partial_file(sftp: &Sftp, path: &str) -> Result<ssh2::File, ssh2::Error> {
+    sftp.open_mode(
+        Path::new(path),
+        OpenFlags::READ | OpenFlags::WRITE | OpenFlags::CREATE,
+        0o600,
+        OpenType::File,
+    )
+}
 
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+/// Открывает недокачанный файл `<dest>.part`. Если каталог назначения закрыт на запись,
+/// файл создается в `STAGING_DIR`, а на место его потом переносит sudo.
+fn open_partial(sftp: &Sftp, dest_path: &str, key: &str) -> Result<(String, ssh2::File), AppError> {
+    let partial_path = partial_name(dest_path);
 
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = dest_session.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S cp '{}' '{}'",
-                dest_connection.password, escaped_temp_file, escaped_dest_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка копирования файла с sudo: {}", sudo_stderr));
-            }
-            
-            let mut cleanup_channel = dest_session.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для очистки: {}", e))?;
-            
-            let cleanup_command = format!("rm '{}'", escaped_temp_file);
-            let _ = cleanup_channel.exec(&cleanup_command);
-            let _ = cleanup_channel.wait_close();
-        } else {
-            return Err(format!("Команда завершилась с ошибкой: {}", stderr));
-        }
+    match open_partial_file(sftp, &partial_path) {
+        Ok(file) => return Ok((partial_path, file)),
+        Err(e) if !is_sftp_permission_denied(&e) => return Err(AppError::ssh(Msg::TempFileCreateFailed.text(), &e)),
+        Err(_) => {}
     }
 
-    let mut chmod_channel = dest_session.channel_session()
-        .map_err(|e| format!("Ошибка создания канала для chmod: {}", e))?;
+    let home = sftp.realpath(Path::new("."))
+        .map_err(|e| AppError::ssh(Msg::TempFileCreateFailed.text(), &e))?;
+    let staging_dir = home.join(STAGING_DIR);
+    let _ = sftp.mkdir(&staging_dir, 0o700);
 
-    let chmod_command = format!("chmod {:o} '{}'", permissions, escaped_dest_path);
-    
-    if let Err(_) = chmod_channel.exec(&chmod_command) {
-        let mut sudo_chmod_channel = dest_session.channel_session()
-            .map_err(|e| format!("Ошибка создания канала для sudo chmod: {}", e))?;
-        
-        let sudo_chmod_command = format!(
-            "echo '{}' | sudo -S chmod {:o} '{}'",
-            dest_connection.password, permissions, escaped_dest_path
-        );
-        
-        let _ = sudo_chmod_channel.exec(&sudo_chmod_command);
-        let _ = sudo_chmod_channel.wait_close();
-    } else {
-        let _ = chmod_channel.wait_close();
-    }
+    let staging_path = staging_dir.join(format!("{}.part", key)).to_string_lossy().to_string();
 
-    Ok(())
+    let file = open_partial_file(sftp, &staging_path)
+        .map_err(|e| AppError::ssh(Msg::TempFileCreateFailed.text(), &e))?;
+
+    Ok((staging_path, file))
 }
 
-fn get_directory_contents(session: &Session, dir_path: &str) -> Result<Vec<(String, bool)>, String> {
-    let mut channel = session.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
+/// Стороны передачи между серверами.
+#[derive(Clone, Copy)]
+struct ServerTransfer<'a> {
+    source_session: &'a Session,
+    dest_session: &'a Session,
+    source_connection: &'a SshConnectionInfo,
+    dest_connection: &'a SshConnectionInfo,
+    overwrite_policy: OverwritePolicy,
+}
 
-    let command = format!("ls -la '{}'", dir_path.replace("'", "'\"'\"'"));
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
+fn transfer_file_content(
+    transfer: &ServerTransfer,
+    source_path: &str,
+    dest_path: &str,
+    summary: &mut TransferSummary,
+) -> Result<(), AppError> {
+    let ServerTransfer { source_session, dest_session, source_connection, dest_connection, overwrite_policy } = *transfer;
 
-    let mut output = String::new();
-    channel.read_to_string(&mut output)
-        .map_err(|e| format!("Ошибка чтения вывода команды: {}", e))?;
+    let dest_sftp = dest_session.sftp()
+        .map_err(|e| AppError::ssh(Msg::DestinationSftpFailed.text(), &e))?;
 
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+    summary.start_file(source_path)?;
 
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        return Err(format!("Команда завершилась с ошибкой: {}", exit_status));
+    if !overwrite_policy.should_copy(dest_sftp.stat(Path::new(dest_path)).is_ok(), dest_path)? {
+        return summary.file_skipped();
     }
 
-    let mut entries = Vec::new();
+    let source_sftp = source_session.sftp()
+        .map_err(|e| AppError::ssh(Msg::SourceSftpFailed.text(), &e))?;
+
+    let permissions = get_file_permissions(source_session, source_path).unwrap_or(0o644);
+
+    let mut source_file = source_sftp.open(Path::new(source_path))
+        .map_err(|e| AppError::ssh(Msg::SourceOpenFailed.text(), &e))?;
+
+    let source_stat = source_file.stat()
+        .map_err(|e| AppError::ssh(Msg::SourceStatFailed.text(), &e))?;
+
+    let resumable = ResumableFile::new(
+        format!("{}:{}", source_connection.endpoint(), source_path),
+        format!("{}:{}", dest_connection.endpoint(), dest_path),
+        SourceVersion {
+            size: source_stat.size,
+            mtime: source_stat.mtime,
+        },
+    );
+
+    // Имя недокачанного файла зависит от назначения: повторная попытка найдет его и продолжит
+    let (partial_path, mut partial_file) = open_partial(&dest_sftp, dest_path, &resumable.key)?;
+    let partial_size = partial_file.stat().ok().and_then(|stat| stat.size);
+
+    let copied = copy_resumable(&resumable, &mut source_file, &mut partial_file, partial_size, summary);
+
+    drop(partial_file);
+
+    if let Err(error) = copied {
+        if summary.is_cancelled() {
+            let _ = dest_sftp.unlink(Path::new(&partial_path));
+        }
+        return Err(error);
+    }
+
+    // Данные уже на месте, sudo нужен только для переноса в закрытый каталог
+    let replace = RemoteCommand::new("mv").arg("-f").arg("--").arg(&partial_path).arg(dest_path);
+    run_with_sudo_fallback(dest_session, dest_connection, &replace, Msg::SudoFileCopyFailed.text())?;
+
+    let chmod = RemoteCommand::new("chmod").arg(format!("{:o}", permissions)).arg(dest_path);
     
-    for line in output.lines().skip(1) {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        
-        if parts.len() >= 9 {
-            let permissions = parts[0];
-            let filename = parts[8..].join(" ");
-            
-            if filename == "." || filename == ".." {
-                continue;
-            }
-            
-            let is_folder = permissions.starts_with('d');
-            entries.push((filename, is_folder));
+    if let Ok(output) = run_command(dest_session, &chmod) {
+        if output.permission_denied() {
+            let _ = run_as_admin(dest_session, dest_connection, &chmod);
         }
     }
 
-    Ok(entries)
+    forget(&resumable.key);
+
+    summary.file_copied()
 }
 
-fn create_directory_if_not_exists(session: &Session, connection_info: &SshConnectionInfo, dir_path: &str) -> Result<(), String> {
-    let mut channel = session.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
+fn get_directory_contents(session: &Session, dir_path: &str) -> Result<Vec<(String, WalkEntry)>, AppError> {
+    let sftp = session.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
 
-    let escaped_path = dir_path.replace("'", "'\"'\"'");
-    let command = format!("test -d '{}' || mkdir -p '{}'", escaped_path, escaped_path);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды mkdir: {}", e))?;
+    let entries = read_directory(&sftp, dir_path)?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| (entry.name, WalkEntry::of(entry.is_symlink, entry.is_folder)))
+        .collect())
+}
 
-    let mut output = String::new();
-    let mut stderr = String::new();
+fn create_directory_if_not_exists(session: &Session, connection_info: &SshConnectionInfo, dir_path: &str) -> Result<(), AppError> {
+    let command = RemoteCommand::new("test").arg("-d").arg(dir_path)
+        .or(RemoteCommand::new("mkdir").arg("-p").arg(dir_path));
+
+    let output = run_command(session, &command)?;
     
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
+    if output.success() {
+        return Ok(());
+    }
 
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+    if !output.permission_denied() {
+        return Err(output.error(Msg::CommandFailed.text()));
+    }
 
-    let exit_status = channel.exit_status().unwrap_or(-1);
+    let sudo_output = run_as_admin(session, connection_info, &command)?;
     
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = session.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S bash -c \"test -d '{}' || mkdir -p '{}'\"",
-                connection_info.password, escaped_path, escaped_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка создания директории с sudo: {}", sudo_stderr));
-            }
-            
-            Ok(())
-        } else {
-            Err(format!("Команда завершилась с ошибкой: {}", stderr))
-        }
-    } else {
-        Ok(())
+    if !sudo_output.success() {
+        return Err(sudo_output.error(Msg::SudoDirectoryCreateFailed.text()));
     }
+    
+    Ok(())
 }
 
 fn transfer_directory_recursive(
-    source_session: &Session,
-    dest_session: &Session,
-    dest_connection: &SshConnectionInfo,
+    transfer: &ServerTransfer,
     source_path: &str,
     dest_path: &str,
-) -> Result<(), String> {
-    create_directory_if_not_exists(dest_session, dest_connection, dest_path)?;
+    summary: &mut TransferSummary,
+) -> Result<(), AppError> {
+    create_directory_if_not_exists(transfer.dest_session, transfer.dest_connection, dest_path)?;
 
-    let entries = get_directory_contents(source_session, source_path)?;
+    let entries = get_directory_contents(transfer.source_session, source_path)?;
     
-    for (filename, is_folder) in entries {
+    for (filename, walk) in entries {
         let source_item_path = format!("{}/{}", source_path, filename);
         let dest_item_path = format!("{}/{}", dest_path, filename);
         
-        if is_folder {
-            transfer_directory_recursive(source_session, dest_session, dest_connection, &source_item_path, &dest_item_path)?;
-        } else {
-            transfer_file_content(source_session, dest_session, dest_connection, &source_item_path, &dest_item_path)?;
+        match walk {
+            WalkEntry::Skip => {}
+            WalkEntry::Folder => transfer_directory_recursive(transfer, &source_item_path, &dest_item_path, summary)?,
+            WalkEntry::File => transfer_file_content(transfer, &source_item_path, &dest_item_path, summary)?,
         }
     }
 
     Ok(())
 }
 
-#[command]
-pub fn transfer_file_between_servers(transfer_request: FileTransferRequest) -> Result<String, String> {
-    let source_session = create_ssh_session(&transfer_request.source_connection)?;
-    let dest_session = create_ssh_session(&transfer_request.destination_connection)?;
+/// Копирует файл или папку между серверами, сообщая о ходе копирования в `summary`.
+pub fn run_server_transfer(pool: &SessionPool, transfer_request: &FileTransferRequest, summary: &mut TransferSummary) -> Result<String, AppError> {
+    let source_session = get_session(pool, &transfer_request.source_connection)?;
+    let dest_session = get_session(pool, &transfer_request.destination_connection)?;
+
+    check_paths(&dest_session, &[(PolicyOperation::Write, &transfer_request.destination_path)], transfer_request.confirmed)?;
+
+    let transfer = ServerTransfer {
+        source_session: &source_session,
+        dest_session: &dest_session,
+        source_connection: &transfer_request.source_connection,
+        dest_connection: &transfer_request.destination_connection,
+        overwrite_policy: transfer_request.overwrite_policy,
+    };
 
     if transfer_request.is_folder {
-        transfer_directory_recursive(
-            &source_session,
-            &dest_session,
-            &transfer_request.destination_connection,
-            &transfer_request.file_path,
-            &transfer_request.destination_path,
-        )?;
+        transfer_directory_recursive(&transfer, &transfer_request.file_path, &transfer_request.destination_path, summary)?;
         
-        Ok(format!("Папка '{}' успешно скопирована", transfer_request.file_path))
+        Ok(summary.describe(Msg::FolderCopied.format(&[&transfer_request.file_path])))
     } else {
-        transfer_file_content(
-            &source_session,
-            &dest_session,
-            &transfer_request.destination_connection,
-            &transfer_request.file_path,
-            &transfer_request.destination_path,
-        )?;
+        transfer_file_content(&transfer, &transfer_request.file_path, &transfer_request.destination_path, summary)?;
         
-        Ok(format!("Файл '{}' успешно скопирован", transfer_request.file_path))
+        if summary.skipped > 0 {
+            return Ok(Msg::FileSkipped.format(&[&transfer_request.destination_path]));
+        }
+
+        Ok(Msg::FileCopied.format(&[&transfer_request.file_path]))
     }
-}
\ No newline at end of file
+}
+
+/// Синхронная передача; для больших объемов используйте фоновые задачи (`start_transfer`).
+#[command]
+pub fn transfer_file_between_servers(pool: State<'_, SessionPool>, transfer_request: FileTransferRequest) -> Result<String, AppError> {
+    run_server_transfer(&pool, &transfer_request, &mut TransferSummary::default())
+}
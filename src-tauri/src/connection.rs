@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use ssh2::Session;
+use std::net::TcpStream;
+
+use crate::auth::authenticate;
+use crate::connection_target::{ConnectionTarget, DEFAULT_SSH_PORT};
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::known_hosts::verify_host_key;
+use crate::privilege::run_privileged;
+use crate::remote_command::{run_command, CommandOutput, RemoteCommand};
+use crate::session_pool::{SessionKey, SessionLease, SessionPool};
+use crate::storage::{AuthMethod, SudoMode};
+
+/// Параметры подключения к серверу, которые фронтенд передает в каждую команду.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SshConnectionInfo {
+    pub username: String,
+    pub host: String,
+    pub password: String,
+    #[serde(default)]
+    pub server_id: Option<u32>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub auth_method: AuthMethod,
+    #[serde(default)]
+    pub sudo: SudoMode,
+    /// Папка корзины из настроек сервера
+    #[serde(default)]
+    pub trash_dir: Option<String>,
+}
+
+impl SshConnectionInfo {
+    /// Пользователь, хост и порт; порт из строки хоста важнее поля `port`.
+    pub fn target(&self) -> Result<ConnectionTarget, AppError> {
+        ConnectionTarget::parse(&self.host, &self.username, self.port.unwrap_or(DEFAULT_SSH_PORT))
+    }
+
+    /// `user@host:port` — идентификатор сервера для журнала недокачанных передач.
+    pub fn endpoint(&self) -> String {
+        format!("{}@{}:{}", self.username, self.host, self.port.unwrap_or(DEFAULT_SSH_PORT))
+    }
+
+    fn session_key(&self) -> SessionKey {
+        // Sudo и корзина не влияют на вход, поэтому их смена не требует переподключения
+        let credentials = (&self.username, &self.host, &self.password, self.port, &self.auth_method);
+        SessionKey::new(self.server_id, &self.username, &self.host, &credentials)
+    }
+}
+
+/// TCP-подключение и рукопожатие SSH без проверки ключа хоста и аутентификации.
+pub fn handshake(target: &ConnectionTarget) -> Result<Session, AppError> {
+    let tcp = TcpStream::connect(target.address())
+        .map_err(|e| AppError::io(Msg::ConnectFailed.text(), &e))?;
+
+    let mut sess = Session::new()
+        .map_err(|e| AppError::ssh(Msg::SessionCreateFailed.text(), &e))?;
+
+    sess.set_tcp_stream(tcp);
+    sess.handshake()
+        .map_err(|e| AppError::ssh(Msg::HandshakeFailed.text(), &e))?;
+
+    Ok(sess)
+}
+
+/// Новая сессия: рукопожатие, проверка ключа хоста и вход выбранным способом.
+/// Единственное место, где устанавливается соединение с сервером.
+pub fn create_session(connection_info: &SshConnectionInfo) -> Result<Session, AppError> {
+    let target = connection_info.target()?;
+    let sess = handshake(&target)?;
+
+    verify_host_key(&sess, &target.host, target.port)?;
+
+    authenticate(&sess, &target.username, &connection_info.password, &connection_info.auth_method)?;
+
+    Ok(sess)
+}
+
+/// Сессия из пула; новая создается, если сервера нет в пуле или изменились учетные данные.
+pub fn get_session(pool: &SessionPool, connection_info: &SshConnectionInfo) -> Result<SessionLease, AppError> {
+    pool.get(&connection_info.session_key(), || create_session(connection_info))
+}
+
+/// Отдельная сессия из пула под именем `name` для того, что нельзя делить с другими
+/// командами (например, терминал в неблокирующем режиме).
+pub fn get_dedicated_session(pool: &SessionPool, connection_info: &SshConnectionInfo, name: &str) -> Result<SessionLease, AppError> {
+    pool.get(&connection_info.session_key().dedicated(name), || create_session(connection_info))
+}
+
+/// Закрывает сессию, полученную через `get_dedicated_session`.
+pub fn close_dedicated_session(pool: &SessionPool, connection_info: &SshConnectionInfo, name: &str, reason: &str) {
+    pool.close(&connection_info.session_key().dedicated(name), reason);
+}
+
+/// Выполняет команду через sudo в режиме, заданном для сервера.
+pub fn run_as_admin(sess: &Session, connection_info: &SshConnectionInfo, command: &RemoteCommand) -> Result<CommandOutput, AppError> {
+    run_privileged(sess, &connection_info.sudo, &connection_info.password, command)
+}
+
+/// Выполняет команду, а при отказе в доступе повторяет ее через sudo.
+/// Возвращает `true`, если понадобились права администратора.
+pub fn run_with_sudo_fallback(
+    sess: &Session,
+    connection_info: &SshConnectionInfo,
+    command: &RemoteCommand,
+    sudo_error: &str,
+) -> Result<bool, AppError> {
+    let output = run_command(sess, command)?;
+
+    // coreutils 9.2–9.4 завершают `cp -n` с ошибкой, если что-то пропущено
+    if output.success() || is_only_skipped(&output.stderr) {
+        return Ok(false);
+    }
+
+    if !output.permission_denied() {
+        return Err(output.error(Msg::CommandFailed.text()));
+    }
+
+    let sudo_output = run_as_admin(sess, connection_info, command)?;
+
+    if !sudo_output.success() && !is_only_skipped(&sudo_output.stderr) {
+        return Err(sudo_output.error(sudo_error));
+    }
+
+    Ok(true)
+}
+
+fn is_only_skipped(stderr: &str) -> bool {
+    !stderr.trim().is_empty() && stderr.lines().all(|line| line.contains("not replacing"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(host: &str, port: Option<u16>) -> SshConnectionInfo {
+        serde_json::from_value(serde_json::json!({
+            "username": "deploy",
+            "host": host,
+            "password": "secret",
+            "port": port,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn port_in_host_overrides_port_field() {
+        assert_eq!(connection("example.com", Some(2222)).target().unwrap().port, 2222);
+        assert_eq!(connection("root@example.com:2200", Some(2222)).target().unwrap().username, "root");
+        assert_eq!(connection("example.com:2200", Some(2222)).target().unwrap().port, 2200);
+    }
+
+    #[test]
+    fn recognizes_skipped_files() {
+        assert!(is_only_skipped("cp: not replacing 'b/a.txt'\n"));
+        assert!(!is_only_skipped("cp: cannot create regular file 'b/a.txt': Permission denied\n"));
+        assert!(!is_only_skipped(""));
+    }
+}
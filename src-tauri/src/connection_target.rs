@@ -0,0 +1,122 @@
+use crate::error::AppError;
+use crate::i18n::Msg;
+
+pub const DEFAULT_SSH_PORT: u16 = 22;
+
+/// Разобранная строка подключения: пользователь, хост и порт.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionTarget {
+    pub username: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl ConnectionTarget {
+    /// Разбирает строки вида `host`, `user@host`, `user@host:port`, `[::1]:2222`,
+    /// `user@[::1]` и `::1`. Имя пользователя отделяется по последнему '@',
+    /// поэтому оно само может содержать '@'. Порт из строки важнее `default_port`.
+    pub fn parse(input: &str, default_username: &str, default_port: u16) -> Result<Self, AppError> {
+        let input = input.trim();
+
+        let (username, host_port) = match input.rsplit_once('@') {
+            Some((user, rest)) => (user, rest),
+            None => (default_username, input),
+        };
+
+        if username.is_empty() {
+            return Err(AppError::invalid_input(Msg::MissingUserName.text()));
+        }
+
+        let (host, port) = split_host_port(host_port)?;
+
+        if host.is_empty() {
+            return Err(AppError::invalid_input(Msg::MissingHost.text()));
+        }
+
+        Ok(ConnectionTarget {
+            username: username.to_string(),
+            host: host.to_string(),
+            port: port.unwrap_or(default_port),
+        })
+    }
+
+    /// Адрес для `TcpStream::connect`; IPv6-адрес передается без квадратных скобок.
+    pub fn address(&self) -> (&str, u16) {
+        (&self.host, self.port)
+    }
+}
+
+fn split_host_port(input: &str) -> Result<(&str, Option<u16>), AppError> {
+    // [IPv6]:port или [IPv6]
+    if let Some(rest) = input.strip_prefix('[') {
+        let (host, after) = rest
+            .split_once(']')
+            .ok_or_else(|| AppError::invalid_input(Msg::UnclosedBracket.format(&[&input])))?;
+
+        return match after {
+            "" => Ok((host, None)),
+            _ => match after.strip_prefix(':') {
+                Some(port) => Ok((host, Some(parse_port(port)?))),
+                None => Err(AppError::invalid_input(Msg::InvalidAddress.format(&[&input]))),
+            },
+        };
+    }
+
+    match input.matches(':').count() {
+        0 => Ok((input, None)),
+        1 => {
+            let (host, port) = input.split_once(':').unwrap_or((input, ""));
+            Ok((host, Some(parse_port(port)?)))
+        }
+        // IPv6 без скобок: порт указать нельзя
+        _ => Ok((input, None)),
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, AppError> {
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => Ok(port),
+        _ => Err(AppError::invalid_input(Msg::InvalidPort.format(&[&port]))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    fn parse(input: &str) -> Result<ConnectionTarget, AppError> {
+        ConnectionTarget::parse(input, "deploy", DEFAULT_SSH_PORT)
+    }
+
+    fn target(username: &str, host: &str, port: u16) -> ConnectionTarget {
+        ConnectionTarget {
+            username: username.to_string(),
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    #[test]
+    fn parses_ipv6_addresses() {
+        assert_eq!(parse("[::1]:2222").unwrap(), target("deploy", "::1", 2222));
+        assert_eq!(parse("user@[::1]").unwrap(), target("user", "::1", 22));
+        assert_eq!(parse("::1").unwrap(), target("deploy", "::1", 22));
+    }
+
+    #[test]
+    fn splits_username_at_last_at_sign() {
+        assert_eq!(parse("me@corp.com@host:2200").unwrap(), target("me@corp.com", "host", 2200));
+        assert_eq!(parse("host").unwrap(), target("deploy", "host", 22));
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        assert_eq!(parse("[::1:22").unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(parse("[::1]22").unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(parse("host:0").unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(parse("host:65536").unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(parse("[::1]:70000").unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(parse("@host").unwrap_err().code, ErrorCode::InvalidInput);
+    }
+}
@@ -0,0 +1,218 @@
+/// Сколько байт от начала файла достаточно, чтобы определить его тип.
+pub const SNIFF_SIZE: usize = 8 * 1024;
+
+/// Доля управляющих символов, после которой файл без NUL все равно считается бинарным.
+const MAX_CONTROL_RATIO: f64 = 0.05;
+
+/// Тип содержимого, определенный по первым байтам файла.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentType {
+    pub mime_type: &'static str,
+    pub is_text: bool,
+}
+
+impl ContentType {
+    fn binary(mime_type: &'static str) -> Self {
+        ContentType {
+            mime_type,
+            is_text: false,
+        }
+    }
+
+    fn text(mime_type: &'static str) -> Self {
+        ContentType {
+            mime_type,
+            is_text: true,
+        }
+    }
+}
+
+/// Сигнатуры распространенных бинарных форматов: смещение, байты и MIME-тип.
+const MAGIC_NUMBERS: &[(usize, &[u8], &str)] = &[
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"MZ", "application/vnd.microsoft.portable-executable"),
+    (0, b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+    (0, b"\xca\xfe\xba\xbe", "application/java-vm"),
+    (0, b"\0asm", "application/wasm"),
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"\0\0\x01\0", "image/x-icon"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"PK\x05\x06", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\0", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+    (0, b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", "application/x-ole-storage"),
+];
+
+/// Определяет тип файла по первым `SNIFF_SIZE` байтам: сигнатуры форматов, BOM,
+/// корректность UTF-8 и доля NUL и управляющих символов. Уточнить тип текста помогает имя файла.
+pub fn sniff(sample: &[u8], file_name: &str) -> ContentType {
+    let sample = &sample[..sample.len().min(SNIFF_SIZE)];
+
+    if sample.is_empty() {
+        return ContentType::text("text/plain");
+    }
+
+    let is_text = looks_like_text(sample);
+
+    if let Some((magic, mime_type)) = magic_mime_type(sample) {
+        // Короткие печатные сигнатуры (`MZ`, `BM`, `ID3`) встречаются и в начале обычного текста
+        if !(is_text && magic.iter().all(|byte| byte.is_ascii_graphic())) {
+            return ContentType::binary(mime_type);
+        }
+    }
+
+    if sample.starts_with(b"RIFF") && sample.len() >= 12 {
+        return ContentType::binary(match &sample[8..12] {
+            b"WEBP" => "image/webp",
+            b"WAVE" => "audio/wav",
+            b"AVI " => "video/x-msvideo",
+            _ => "application/octet-stream",
+        });
+    }
+
+    if sample.len() >= 12 && &sample[4..8] == b"ftyp" {
+        return ContentType::binary("video/mp4");
+    }
+
+    if !is_text {
+        return ContentType::binary("application/octet-stream");
+    }
+
+    ContentType::text(text_mime_type(sample, file_name))
+}
+
+fn magic_mime_type(sample: &[u8]) -> Option<(&'static [u8], &'static str)> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(offset, magic, _)| sample.get(*offset..*offset + magic.len()) == Some(*magic))
+        .map(|(_, magic, mime_type)| (*magic, *mime_type))
+}
+
+fn looks_like_text(sample: &[u8]) -> bool {
+    // UTF-16 и UTF-32 с BOM содержат NUL, но остаются текстом
+    if sample.starts_with(b"\xef\xbb\xbf") || sample.starts_with(b"\xff\xfe") || sample.starts_with(b"\xfe\xff") {
+        return true;
+    }
+
+    if sample.contains(&0) {
+        return false;
+    }
+
+    let valid_utf8 = match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        // Выборка могла разрезать многобайтовый символ в конце
+        Err(e) => e.error_len().is_none(),
+    };
+
+    if valid_utf8 {
+        return true;
+    }
+
+    // Не UTF-8, но без NUL: вероятно, однобайтовая кодировка (CP1251, KOI8-R),
+    // если управляющих символов немного
+    let control = sample
+        .iter()
+        .filter(|byte| byte.is_ascii_control() && !matches!(byte, b'\t' | b'\n' | b'\r' | b'\x0c' | b'\x1b'))
+        .count();
+
+    (control as f64) < sample.len() as f64 * MAX_CONTROL_RATIO
+}
+
+fn text_mime_type(sample: &[u8], file_name: &str) -> &'static str {
+    let head = String::from_utf8_lossy(&sample[..sample.len().min(256)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    let head_lower = head.to_lowercase();
+
+    if let Some(shebang) = head.strip_prefix("#!") {
+        let interpreter = shebang.lines().next().unwrap_or("");
+        return if interpreter.contains("python") {
+            "text/x-python"
+        } else if interpreter.contains("perl") {
+            "text/x-perl"
+        } else if interpreter.contains("node") {
+            "text/javascript"
+        } else {
+            "text/x-shellscript"
+        };
+    }
+
+    if head.starts_with("-----BEGIN ") {
+        return "application/x-pem-file";
+    }
+
+    if head_lower.starts_with("<?xml") {
+        return "application/xml";
+    }
+
+    if head_lower.starts_with("<!doctype html") || head_lower.starts_with("<html") {
+        return "text/html";
+    }
+
+    let extension = file_name.rsplit_once('.').map(|(_, extension)| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js" | "mjs" | "cjs") => "text/javascript",
+        Some("ts") => "text/x-typescript",
+        Some("md") => "text/markdown",
+        Some("yaml" | "yml") => "application/yaml",
+        Some("toml") => "application/toml",
+        Some("sh" | "bash") => "text/x-shellscript",
+        Some("py") => "text/x-python",
+        Some("rs") => "text/x-rust",
+        Some("c" | "h") => "text/x-c",
+        Some("cpp" | "hpp" | "cc") => "text/x-c++",
+        Some("go") => "text/x-go",
+        Some("java") => "text/x-java",
+        Some("php") => "text/x-php",
+        Some("sql") => "application/sql",
+        Some("csv") => "text/csv",
+        _ => "text/plain",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_binary_formats() {
+        assert_eq!(sniff(b"\x7fELF\x02\x01\x01\0", "nginx"), ContentType::binary("application/x-executable"));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "logo.txt"), ContentType::binary("image/png"));
+        assert_eq!(sniff(b"data\0\0\x01more", "dump.conf"), ContentType::binary("application/octet-stream"));
+    }
+
+    #[test]
+    fn recognizes_text_regardless_of_extension() {
+        assert_eq!(sniff(b"#!/usr/bin/env python3\nprint(1)\n", "deploy"), ContentType::text("text/x-python"));
+        assert_eq!(sniff(b"server {\n    listen 80;\n}\n", "default"), ContentType::text("text/plain"));
+        assert_eq!(sniff(b"{\"a\": 1}", "package.json"), ContentType::text("application/json"));
+        assert_eq!(sniff(b"", "empty.bin"), ContentType::text("text/plain"));
+        assert_eq!(sniff(b"BMW fleet list\n", "cars"), ContentType::text("text/plain"));
+    }
+
+    #[test]
+    fn accepts_legacy_cyrillic_text() {
+        // "# настройки" в CP1251
+        let cp1251 = b"# \xed\xe0\xf1\xf2\xf0\xee\xe9\xea\xe8\nport = 80\n";
+        assert!(sniff(cp1251, "app.conf").is_text);
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io;
+
+use crate::i18n::Msg;
+
+/// Стабильный код ошибки: по нему интерфейс решает, что показать, не разбирая текст сообщения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Сервер отклонил логин, пароль или ключ
+    AuthFailed,
+    /// Не удалось установить соединение или оно оборвалось
+    HostUnreachable,
+    /// Ключ хоста еще не сохранен; в `details` — отпечаток для подтверждения
+    HostKeyUnknown,
+    /// Ключ хоста не совпадает с сохраненным
+    HostKeyMismatch,
+    PermissionDenied,
+    NotFound,
+    /// Путь уже существует или файл изменился на сервере после чтения
+    Conflict,
+    /// Некорректные аргументы команды или настройки
+    InvalidInput,
+    /// Операция запрещена политикой защищенных путей
+    PolicyDenied,
+    /// Операция разрешена только после подтверждения пользователя
+    ConfirmationRequired,
+    /// Хранилище паролей заблокировано или мастер-пароль неверный
+    VaultLocked,
+    /// Команда на сервере завершилась с ошибкой; в `details` — код и stderr
+    CommandFailed,
+    /// Файл слишком большой или не текстовый для этой операции
+    Unsupported,
+    Cancelled,
+    /// Прочие ошибки SSH, SFTP и файловой системы
+    Io,
+    Internal,
+}
+
+/// Ошибка команды приложения: код, сообщение для пользователя и данные для интерфейса.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Отпечаток ключа, версии файла, правило политики и т.п.
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError {
+            code,
+            message: message.into(),
+            details: Value::Null,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::Conflict, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::new(ErrorCode::Internal, message)
+    }
+
+    /// Ошибка libssh2 с пояснением: `"{context}: {error}"`, код определяется по коду libssh2.
+    pub fn ssh(context: &str, error: &ssh2::Error) -> Self {
+        AppError::new(ssh_error_code(error), format!("{}: {}", context, error))
+    }
+
+    /// Ошибка ввода-вывода с пояснением: `"{context}: {error}"`.
+    pub fn io(context: &str, error: &io::Error) -> Self {
+        AppError::new(io_error_code(error), format!("{}: {}", context, error))
+    }
+
+    /// Ошибка, у которой нет своего кода: сериализация, разбор JSON, криптография.
+    pub fn other(context: &str, error: impl fmt::Display) -> Self {
+        AppError::internal(format!("{}: {}", context, error))
+    }
+
+    /// Неудачная команда на сервере. Отказ в доступе и отсутствие пути узнаются по stderr.
+    pub fn command(context: &str, exit_status: i32, stderr: &str) -> Self {
+        let code = if is_permission_denied(stderr) {
+            ErrorCode::PermissionDenied
+        } else if stderr.contains("No such file or directory") {
+            ErrorCode::NotFound
+        } else {
+            ErrorCode::CommandFailed
+        };
+
+        AppError::new(code, Msg::CommandExitStatus.format(&[&context, &exit_status, &stderr.trim_end()]))
+            .with_details(json!({ "exit_status": exit_status, "stderr": stderr }))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Отказ в доступе в выводе команды, включая `Operation not permitted` от chown/chmod.
+pub fn is_permission_denied(stderr: &str) -> bool {
+    stderr.contains("Permission denied")
+        || stderr.contains("permission denied")
+        || stderr.contains("Operation not permitted")
+}
+
+/// Код SSH_FX_PERMISSION_DENIED протокола SFTP.
+const SFTP_PERMISSION_DENIED: i32 = 3;
+
+/// Отказ в доступе в ответе SFTP-сервера.
+pub fn is_sftp_permission_denied(error: &ssh2::Error) -> bool {
+    matches!(error.code(), ssh2::ErrorCode::SFTP(SFTP_PERMISSION_DENIED))
+}
+
+fn ssh_error_code(error: &ssh2::Error) -> ErrorCode {
+    match error.code() {
+        // LIBSSH2_ERROR_AUTHENTICATION_FAILED, PUBLICKEY_UNVERIFIED, PASSWORD_EXPIRED, KEYFILE_AUTH_FAILED
+        ssh2::ErrorCode::Session(-18 | -19 | -15 | -48) => ErrorCode::AuthFailed,
+        // Баннер, обмен ключами, таймауты и обрывы сокета
+        ssh2::ErrorCode::Session(-1 | -2 | -3 | -5 | -7 | -8 | -9 | -13 | -30 | -43 | -45) => ErrorCode::HostUnreachable,
+        ssh2::ErrorCode::Session(-16) => ErrorCode::NotFound,
+        // LIBSSH2_FX_NO_SUCH_FILE, NO_SUCH_PATH
+        ssh2::ErrorCode::SFTP(2 | 10) => ErrorCode::NotFound,
+        // LIBSSH2_FX_PERMISSION_DENIED, WRITE_PROTECT
+        ssh2::ErrorCode::SFTP(3 | 12) => ErrorCode::PermissionDenied,
+        // LIBSSH2_FX_FILE_ALREADY_EXISTS, DIR_NOT_EMPTY
+        ssh2::ErrorCode::SFTP(11 | 18) => ErrorCode::Conflict,
+        // LIBSSH2_FX_NO_CONNECTION, CONNECTION_LOST
+        ssh2::ErrorCode::SFTP(6 | 7) => ErrorCode::HostUnreachable,
+        _ => ErrorCode::Io,
+    }
+}
+
+fn io_error_code(error: &io::Error) -> ErrorCode {
+    match error.kind() {
+        io::ErrorKind::NotFound => ErrorCode::NotFound,
+        io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+        io::ErrorKind::AlreadyExists => ErrorCode::Conflict,
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::HostUnreachable
+        | io::ErrorKind::NetworkUnreachable
+        | io::ErrorKind::AddrNotAvailable
+        | io::ErrorKind::TimedOut => ErrorCode::HostUnreachable,
+        _ => ErrorCode::Io,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let error = AppError::conflict("Файл изменился").with_details(json!({ "path": "/etc/hosts" }));
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({ "code": "conflict", "message": "Файл изменился", "details": { "path": "/etc/hosts" } })
+        );
+        assert_eq!(serde_json::to_value(AppError::internal("x")).unwrap(), json!({ "code": "internal", "message": "x" }));
+    }
+
+    #[test]
+    fn classifies_underlying_errors() {
+        let auth = ssh2::Error::new(ssh2::ErrorCode::Session(-18), "Authentication failed");
+        assert_eq!(AppError::ssh("Ошибка аутентификации", &auth).code, ErrorCode::AuthFailed);
+
+        let missing = ssh2::Error::new(ssh2::ErrorCode::SFTP(2), "no such file");
+        assert_eq!(AppError::ssh("Ошибка чтения", &missing).code, ErrorCode::NotFound);
+
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(AppError::io("Ошибка подключения", &refused).code, ErrorCode::HostUnreachable);
+
+        assert_eq!(AppError::command("Ошибка", 1, "rm: cannot remove 'x': Permission denied\n").code, ErrorCode::PermissionDenied);
+        assert_eq!(AppError::command("Ошибка", 2, "boom").code, ErrorCode::CommandFailed);
+    }
+}
@@ -1,15 +1,22 @@
 use serde::{Deserialize, Serialize};
-use ssh2::Session;
-use std::io::{Read};
-use std::net::TcpStream;
-use tauri::command;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SshConnectionInfo {
-    pub username: String,
-    pub host: String,
-    pub password: String,
-}
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use ssh2::{FileStat, OpenFlags, OpenType, Session, Sftp};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, State};
+
+use crate::content_type::{sniff, ContentType, SNIFF_SIZE};
+use crate::connection::{get_session, run_as_admin, SshConnectionInfo};
+use crate::error::{is_permission_denied, is_sftp_permission_denied, AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::remote_command::{run_command, RemoteCommand};
+use crate::session_pool::SessionPool;
+use crate::text_encoding::TextFormat;
+
+/// Права нового файла, если сохраняемого файла еще не было.
+const DEFAULT_FILE_MODE: u32 = 0o644;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileContent {
@@ -19,277 +26,176 @@ pub struct FileContent {
     pub is_symlink: bool,
     pub symlink_target: Option<String>,
     pub file_size: Option<u64>,
+    /// Версия прочитанного содержимого; передается обратно в `save_file_content`
+    pub version: Option<FileVersion>,
+    /// Кодировка и переводы строк файла; `content` всегда в UTF-8 с `\n`
+    pub text_format: Option<TextFormat>,
+    /// Файл можно открыть в шестнадцатеричном просмотре (`read_file_hex`)
+    pub hex_view_available: bool,
 }
 
-
-fn is_text_file(filename: &str) -> bool {
-    let text_extensions = [
-        "txt", "md", "js", "ts", "html", "css", "scss", "json", "xml", "yaml", "yml",
-        "toml", "ini", "cfg", "conf", "log", "sh", "py", "rs", "go", "php", "cpp",
-        "c", "h", "hpp", "java", "kt", "swift", "rb", "pl", "lua", "sql", "vue",
-        "jsx", "tsx", "scss", "less", "styl", "mod", "env", "dockerfile", "makefile",
-        "gitignore", "gitconfig", "editorconfig", "prettierrc", "eslintrc", "babelrc",
-        "npmrc", "yarnrc", "nvmrc", "bashrc", "zshrc", "vimrc", "tmux", "profile",
-        "bashprofile", "zshprofile", "fish", "tcsh", "csh", "ksh", "bat", "cmd",
-        "ps1", "psm1", "psd1", "ps1xml", "pssc", "psrc", "psc1", "R", "r", "Rmd",
-        "tex", "bib", "cls", "sty", "dtx", "ins", "ltx", "rtx", "ctx", "lco",
-        "def", "fd", "bbx", "cbx", "lbx", "TeX", "LaTeX", "bibtex", "context",
-        "readme", "license", "changelog", "authors", "contributors", "copying",
-        "install", "news", "todo", "version", "history", "notice", "acknowledgments"
-    ];
-    
-    if let Some(ext) = filename.split('.').last() {
-        let ext_lower = ext.to_lowercase();
-        text_extensions.contains(&ext_lower.as_str())
-    } else {
-        let filename_lower = filename.to_lowercase();
-        text_extensions.contains(&filename_lower.as_str()) ||
-        filename_lower.starts_with("readme") ||
-        filename_lower.starts_with("license") ||
-        filename_lower.starts_with("changelog") ||
-        filename_lower.starts_with("makefile") ||
-        filename_lower.starts_with("dockerfile") ||
-        filename_lower.starts_with(".env") ||
-        filename_lower.starts_with(".git")
-    }
+/// Версия файла на сервере: время изменения, размер и SHA-256 содержимого.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileVersion {
+    pub mtime: Option<u64>,
+    pub size: u64,
+    pub hash: String,
 }
 
-fn is_likely_text_file(file_path: &str, content: &str) -> bool {
-    let filename = file_path.split('/').last().unwrap_or(file_path);
-    
-    if is_text_file(filename) {
-        return true;
-    }
-    
-    let path_lower = file_path.to_lowercase();
-    let common_text_paths = [
-        "/etc/", "/usr/share/", "/var/log/", "/home/", "/root/", "/opt/",
-        "/usr/local/", "/srv/", "/var/www/", "/var/lib/", "/usr/bin/",
-        "/usr/sbin/", "/bin/", "/sbin/"
-    ];
-    
-    for path in &common_text_paths {
-        if path_lower.starts_with(path) {
-            break;
+impl FileVersion {
+    fn of(content: &[u8], mtime: Option<u64>) -> Self {
+        FileVersion {
+            mtime,
+            size: content.len() as u64,
+            hash: content_hash(content),
         }
     }
-    
-    if content.len() > 1000 {
-        let sample = &content[..1000];
-        let non_printable_count = sample.chars()
-            .filter(|c| !c.is_ascii_graphic() && !c.is_ascii_whitespace())
-            .count();
-        
-        return non_printable_count < 50;
-    }
-    
-    let non_printable_count = content.chars()
-        .filter(|c| !c.is_ascii_graphic() && !c.is_ascii_whitespace())
-        .count();
-    
-    return non_printable_count < (content.len() / 10);
 }
 
-
-fn get_file_type(filename: &str) -> String {
-    if let Some(ext) = filename.split('.').last() {
-        ext.to_lowercase()
-    } else {
-        "unknown".to_string()
-    }
+#[derive(Debug, Serialize)]
+pub struct SaveResult {
+    pub message: String,
+    /// Новая версия файла для следующего сохранения
+    pub version: FileVersion,
+    pub backup_path: Option<String>,
+    /// Владелец и права исходного файла; для нового файла все поля пустые
+    pub preserved: PreservedAttributes,
+    /// Кодировка и переводы строк, в которых записан файл
+    pub text_format: TextFormat,
 }
 
-fn create_ssh_session(connection_info: &SshConnectionInfo) -> Result<Session, String> {
-    let host_string = if connection_info.host.contains('@') {
-        connection_info.host.clone()
-    } else {
-        format!("{}@{}", connection_info.username, connection_info.host)
-    };
-
-    let parts: Vec<&str> = host_string.split('@').collect();
-    
-    if parts.len() != 2 {
-        return Err("Неверный формат строки подключения".to_string());
-    }
-    
-    let username = parts[0];
-    let host = parts[1];
-
-    let tcp = TcpStream::connect(format!("{}:22", host))
-        .map_err(|e| format!("Ошибка подключения к серверу: {}", e))?;
-
-    let mut sess = Session::new()
-        .map_err(|e| format!("Ошибка создания SSH-сессии: {}", e))?;
 
-    sess.set_tcp_stream(tcp);
-    sess.handshake()
-        .map_err(|e| format!("Ошибка при рукопожатии SSH: {}", e))?;
-
-    sess.userauth_password(username, &connection_info.password)
-        .map_err(|e| format!("Ошибка аутентификации: {}", e))?;
-
-    Ok(sess)
+/// Результат `stat` для открываемого файла.
+struct RemoteFileInfo {
+    is_symlink: bool,
+    symlink_target: Option<String>,
+    size: Option<u64>,
 }
 
-fn check_file_info(sess: &Session, file_path: &str) -> Result<(bool, Option<String>, Option<u64>), String> {
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
+fn check_file_info(sess: &Session, file_path: &str) -> Result<RemoteFileInfo, AppError> {
+    let command = RemoteCommand::new("stat").arg("-c").arg("%F|%s").arg(file_path).stderr_to_null()
+        .or(RemoteCommand::new("echo").arg("error"));
 
-    let command = format!("stat -c '%F|%s' '{}' 2>/dev/null || echo 'error'", file_path.replace("'", "'\"'\"'"));
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
+    let output = run_command(sess, &command)?.stdout;
 
-    let mut output = String::new();
-    channel.read_to_string(&mut output)
-        .map_err(|e| format!("Ошибка чтения вывода: {}", e))?;
-
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+    let unknown = RemoteFileInfo {
+        is_symlink: false,
+        symlink_target: None,
+        size: None,
+    };
 
     if output.trim() == "error" {
-        return Ok((false, None, None));
+        return Ok(unknown);
     }
 
     let parts: Vec<&str> = output.trim().split('|').collect();
     if parts.len() != 2 {
-        return Ok((false, None, None));
+        return Ok(unknown);
     }
 
     let file_type = parts[0];
-    let file_size = parts[1].parse::<u64>().ok();
+    let size = parts[1].parse::<u64>().ok();
     
     let is_symlink = file_type.contains("symbolic link");
     
     let symlink_target = if is_symlink {
-        let mut link_channel = sess.channel_session()
-            .map_err(|e| format!("Ошибка создания канала для readlink: {}", e))?;
-        
-        let readlink_command = format!("readlink '{}'", file_path.replace("'", "'\"'\"'"));
-        
-        if link_channel.exec(&readlink_command).is_ok() {
-            let mut link_output = String::new();
-            if link_channel.read_to_string(&mut link_output).is_ok() {
-                let _ = link_channel.wait_close();
-                Some(link_output.trim().to_string())
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        run_command(sess, &RemoteCommand::new("readlink").arg(file_path))
+            .ok()
+            .map(|output| output.stdout.trim().to_string())
     } else {
         None
     };
 
-    Ok((is_symlink, symlink_target, file_size))
+    Ok(RemoteFileInfo {
+        is_symlink,
+        symlink_target,
+        size,
+    })
 }
 
-fn is_binary_file(sess: &Session, file_path: &str) -> bool {
-    let mut channel = match sess.channel_session() {
-        Ok(ch) => ch,
-        Err(_) => return true,
-    };
+/// Открывает файл и читает первые `SNIFF_SIZE` байт, по которым определяется его тип.
+fn open_and_sniff(sftp: &Sftp, file_path: &str) -> Result<(ssh2::File, Vec<u8>, ContentType), AppError> {
+    let mut file = sftp.open(Path::new(file_path))
+        .map_err(|e| AppError::ssh(Msg::FileReadFailed.text(), &e))?;
 
-    let command = format!("file -b --mime-type '{}'", file_path.replace("'", "'\"'\"'"));
-    
-    if channel.exec(&command).is_err() {
-        return true;
-    }
+    let mut sample = Vec::with_capacity(SNIFF_SIZE);
+    (&mut file).take(SNIFF_SIZE as u64).read_to_end(&mut sample)
+        .map_err(|e| AppError::io(Msg::FileReadFailed.text(), &e))?;
 
-    let mut output = String::new();
-    if channel.read_to_string(&mut output).is_err() {
-        return true;
-    }
+    let content_type = sniff(&sample, file_name(file_path));
+    Ok((file, sample, content_type))
+}
 
-    let _ = channel.wait_close();
-    
-    let mime_type = output.trim().to_lowercase();
-    !mime_type.starts_with("text/") && !mime_type.contains("json") && !mime_type.contains("xml")
+fn file_name(file_path: &str) -> &str {
+    file_path.rsplit('/').next().unwrap_or(file_path)
 }
 
 #[command]
-pub fn read_file_content(connection_info: SshConnectionInfo, file_path: String) -> Result<FileContent, String> {
-    let sess = create_ssh_session(&connection_info)?;
+pub fn read_file_content(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, file_path: String) -> Result<FileContent, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
     
-    let filename = file_path.split('/').last().unwrap_or(&file_path);
-    let file_type = get_file_type(filename);
-    
-    let (is_symlink, symlink_target, file_size) = check_file_info(&sess, &file_path)?;
+    let RemoteFileInfo { is_symlink, symlink_target, size: file_size } = check_file_info(&sess, &file_path)?;
     
     if is_symlink {
-        let target_display = symlink_target.as_ref().map(|t| t.as_str()).unwrap_or("неизвестно");
+        let target_display = symlink_target.as_deref().unwrap_or(Msg::UnknownTarget.text());
         return Ok(FileContent {
-            content: format!("Символическая ссылка\nПуть: {}\nСсылается на: {}", file_path, target_display),
+            content: Msg::SymlinkPreview.format(&[&file_path, &target_display]),
             is_editable: false,
-            file_type,
+            file_type: "inode/symlink".to_string(),
             is_symlink: true,
             symlink_target,
             file_size,
+            version: None,
+            text_format: None,
+            hex_view_available: false,
         });
     }
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let (mut file, mut bytes, content_type) = open_and_sniff(&sftp, &file_path)?;
+    let file_type = content_type.mime_type.to_string();
     
     if let Some(size) = file_size {
         if size > 10_000_000 {
             return Ok(FileContent {
-                content: format!("Файл слишком большой для просмотра\nРазмер: {} байт\nПуть: {}", size, file_path),
+                content: Msg::LargeFilePreview.format(&[&size, &file_path]),
                 is_editable: false,
                 file_type,
                 is_symlink: false,
                 symlink_target: None,
                 file_size,
+                version: None,
+                text_format: None,
+                hex_view_available: true,
             });
         }
     }
 
-    let is_text = is_text_file(filename);
-    let is_binary = if is_text { false } else { is_binary_file(&sess, &file_path) };
-
-    if is_binary {
-        let mut channel = sess.channel_session()
-            .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-        let command = format!("file -b '{}'", file_path.replace("'", "'\"'\"'"));
-        
-        channel.exec(&command)
-            .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
-
-        let mut file_info = String::new();
-        channel.read_to_string(&mut file_info)
-            .map_err(|e| format!("Ошибка получения информации о файле: {}", e))?;
-
-        let _ = channel.wait_close();
-
+    if !content_type.is_text {
         return Ok(FileContent {
-            content: format!("Бинарный файл\nТип: {}\nПуть: {}\nИнформация: {}", file_type, file_path, file_info.trim()),
+            content: Msg::BinaryFilePreview.format(&[&file_type, &file_path]),
             is_editable: false,
             file_type,
             is_symlink: false,
             symlink_target: None,
             file_size,
+            version: None,
+            text_format: None,
+            hex_view_available: true,
         });
     }
 
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-    let command = format!("cat '{}'", file_path.replace("'", "'\"'\"'"));
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
-
-    let mut content = String::new();
-    channel.read_to_string(&mut content)
-        .map_err(|e| format!("Ошибка чтения файла: {}", e))?;
+    file.read_to_end(&mut bytes)
+        .map_err(|e| AppError::io(Msg::FileReadFailed.text(), &e))?;
 
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+    let text_format = TextFormat::detect(&bytes);
+    let content = text_format.decode(&bytes)?;
 
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        return Err("Ошибка чтения файла".to_string());
-    }
+    // Время изменения берется из SFTP, как и при проверке перед сохранением, иначе версии не совпадут
+    let mtime = sftp.stat(Path::new(&file_path)).ok().and_then(|stat| stat.mtime);
+    let version = FileVersion::of(&bytes, mtime);
 
     Ok(FileContent {
         content,
@@ -298,97 +204,391 @@ pub fn read_file_content(connection_info: SshConnectionInfo, file_path: String)
         is_symlink: false,
         symlink_target: None,
         file_size,
+        version: Some(version),
+        text_format: Some(text_format),
+        hex_view_available: true,
+    })
+}
+
+fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn read_remote_file(sftp: &Sftp, file_path: &str) -> Result<Vec<u8>, AppError> {
+    let mut file = sftp.open(Path::new(file_path))
+        .map_err(|e| AppError::ssh(Msg::ConflictCheckReadFailed.text(), &e))?;
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)
+        .map_err(|e| AppError::io(Msg::ConflictCheckReadFailed.text(), &e))?;
+
+    Ok(content)
+}
+
+/// Сохраняемый файл в том виде, в каком он сейчас лежит на сервере.
+struct ExistingFile {
+    content: Vec<u8>,
+    mtime: Option<u64>,
+}
+
+/// Текущее содержимое файла на сервере или `None`, если файла нет.
+fn read_existing(sftp: &Sftp, file_path: &str) -> Result<Option<ExistingFile>, AppError> {
+    let stat = match sftp.stat(Path::new(file_path)) {
+        Ok(stat) => stat,
+        Err(_) => return Ok(None),
+    };
+
+    let content = read_remote_file(sftp, file_path)?;
+
+    Ok(Some(ExistingFile {
+        content,
+        mtime: stat.mtime,
+    }))
+}
+
+/// Отклоняет сохранение, если файл изменился после того, как его открыли в редакторе.
+fn check_conflict(file_path: &str, existing: Option<&ExistingFile>, expected: &FileVersion) -> Result<(), AppError> {
+    let actual = existing.map(|existing| FileVersion::of(&existing.content, existing.mtime));
+
+    if actual.as_ref() == Some(expected) {
+        return Ok(());
+    }
+
+    let message = match actual {
+        Some(_) => Msg::FileChangedOnServer.format(&[&file_path]),
+        None => Msg::FileDeletedOnServer.format(&[&file_path]),
+    };
+
+    // По версиям в `details` редактор предлагает перечитать файл или перезаписать его
+    Err(AppError::conflict(message).with_details(json!({
+        "path": file_path,
+        "expected": expected,
+        "actual": actual,
+    })))
+}
+
+fn unique_suffix() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    format!("{}-{}", std::process::id(), nanos)
+}
+
+/// Временный файл рядом с сохраняемым: `rename` в пределах каталога атомарен.
+fn sibling_temp_path(file_path: &str) -> String {
+    let (dir, name) = file_path.rsplit_once('/').unwrap_or((".", file_path));
+
+    format!("{}/.{}.ssh-connect-{}.tmp", dir, name, unique_suffix())
+}
+
+fn create_remote_file(sftp: &Sftp, path: &str, mode: u32) -> Result<ssh2::File, ssh2::Error> {
+    sftp.open_mode(
+        Path::new(path),
+        OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::EXCLUSIVE,
+        mode as i32,
+        OpenType::File,
+    )
+}
+
+/// Записывает содержимое в только что созданный файл; при ошибке файл удаляется.
+fn write_remote_file(sftp: &Sftp, mut file: ssh2::File, path: &str, content: &[u8]) -> Result<(), AppError> {
+    let result = file.write_all(content);
+    drop(file);
+
+    result.map_err(|e| {
+        let _ = sftp.unlink(Path::new(path));
+        AppError::io(Msg::TempFileWriteFailed.text(), &e)
     })
 }
 
+/// Владелец, группа и права исходного файла, которые сохраняются после замены.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileAttributes {
+    owner: Option<(u32, u32)>,
+    mode: u32,
+}
+
+impl FileAttributes {
+    fn of(stat: Option<&FileStat>) -> Self {
+        FileAttributes {
+            owner: stat.and_then(|stat| stat.uid.zip(stat.gid)),
+            mode: stat.and_then(|stat| stat.perm).map(|perm| perm & 0o7777).unwrap_or(DEFAULT_FILE_MODE),
+        }
+    }
+
+    fn owner_matches(&self, stat: &FileStat) -> bool {
+        self.owner.is_none_or(|owner| stat.uid.zip(stat.gid) == Some(owner))
+    }
+
+    fn mode_matches(&self, stat: &FileStat) -> bool {
+        stat.perm.map(|perm| perm & 0o7777) == Some(self.mode)
+    }
+
+    /// `chown uid:gid path && chmod mode path`. chmod идет вторым: chown сбрасывает setuid/setgid.
+    fn restore_command(&self, path: &str) -> RemoteCommand {
+        let chmod = RemoteCommand::new("chmod").arg(format!("{:o}", self.mode)).arg(path);
+
+        match self.owner {
+            Some((uid, gid)) => RemoteCommand::new("chown").arg(format!("{}:{}", uid, gid)).arg(path).and(chmod),
+            None => chmod,
+        }
+    }
+}
+
+/// Что из атрибутов исходного файла удалось сохранить.
+#[derive(Debug, Serialize)]
+pub struct PreservedAttributes {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub mode: Option<u32>,
+    pub owner_preserved: bool,
+    pub mode_preserved: bool,
+}
+
+impl PreservedAttributes {
+    fn check(sftp: &Sftp, file_path: &str, original: Option<&FileAttributes>) -> Self {
+        let current = sftp.stat(Path::new(file_path)).ok();
+        let matches = |check: fn(&FileAttributes, &FileStat) -> bool| match (original, &current) {
+            (Some(original), Some(current)) => check(original, current),
+            _ => false,
+        };
+
+        PreservedAttributes {
+            uid: original.and_then(|attributes| attributes.owner).map(|(uid, _)| uid),
+            gid: original.and_then(|attributes| attributes.owner).map(|(_, gid)| gid),
+            mode: original.map(|attributes| attributes.mode),
+            owner_preserved: matches(FileAttributes::owner_matches),
+            mode_preserved: matches(FileAttributes::mode_matches),
+        }
+    }
+}
+
+/// Переносит владельца и права на временный файл средствами SFTP. Сменить владельца
+/// без root обычно нельзя, поэтому возвращает, совпадает ли теперь владелец.
+fn apply_attributes(sftp: &Sftp, path: &str, attributes: &FileAttributes) -> bool {
+    let path = Path::new(path);
+    let set = |uid, gid, perm| {
+        sftp.setstat(path, FileStat {
+            size: None,
+            uid,
+            gid,
+            perm,
+            atime: None,
+            mtime: None,
+        })
+    };
+
+    if let Some((uid, gid)) = attributes.owner {
+        let _ = set(Some(uid), Some(gid), None);
+    }
+    let _ = set(None, None, Some(attributes.mode));
+
+    sftp.stat(path).map(|stat| attributes.owner_matches(&stat)).unwrap_or(false)
+}
+
+/// chown отказал даже под sudo: например, root на NFS-ресурсе с root_squash.
+fn chown_refused(stderr: &str) -> bool {
+    stderr.lines().any(|line| line.starts_with("chown:") && is_permission_denied(line))
+}
+
+/// `cp -p path path.bak` (если нужна копия) и `mv -f temp path`.
+fn replace_command(temp_path: &str, file_path: &str, backup_path: Option<&str>) -> RemoteCommand {
+    let replace = RemoteCommand::new("mv").arg("-f").arg("--").arg(temp_path).arg(file_path);
+
+    match backup_path {
+        Some(backup_path) => RemoteCommand::new("cp").arg("-p").arg("--").arg(file_path).arg(backup_path).and(replace),
+        None => replace,
+    }
+}
+
+/// Записывает содержимое во временный файл в том же каталоге и переименовывает его поверх
+/// исходного, поэтому при сбое исходный файл остается целым. Владелец, группа и права
+/// исходного файла переносятся на новый, при необходимости через sudo. Без владельца
+/// (`owner_preserved: false`) файл сохраняется, только если chown отказал даже под sudo или
+/// передан `drop_owner`; иначе возвращается ошибка sudo с `owner_droppable` в `details`. Если файл изменился
+/// на сервере после чтения (`expected_version`), возвращается ошибка `conflict`.
+/// Без `expected_version` файл перезаписывается без проверки. Текст записывается в формате
+/// `text_format`, а если он не передан — в кодировке и с переводами строк текущего файла.
 #[command]
-pub fn save_file_content(connection_info: SshConnectionInfo, file_path: String, content: String) -> Result<String, String> {
-    if !is_likely_text_file(&file_path, &content) {
-        return Err("Этот тип файла нельзя редактировать".to_string());
+#[allow(clippy::too_many_arguments)]
+pub fn save_file_content(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    file_path: String,
+    content: String,
+    expected_version: Option<FileVersion>,
+    keep_backup: Option<bool>,
+    text_format: Option<TextFormat>,
+    drop_owner: Option<bool>,
+) -> Result<SaveResult, AppError> {
+    if !sniff(content.as_bytes(), file_name(&file_path)).is_text {
+        return Err(AppError::new(ErrorCode::Unsupported, Msg::FileNotEditable.text()));
     }
 
-    let sess = create_ssh_session(&connection_info)?;
-    
-    let temp_file = format!("/tmp/ssh_editor_{}", std::process::id());
-    
+    let sess = get_session(&pool, &connection_info)?;
+
     let sftp = sess.sftp()
-        .map_err(|e| format!("Ошибка создания SFTP канала: {}", e))?;
-    
-    {
-        let mut file = sftp.create(&std::path::Path::new(&temp_file))
-            .map_err(|e| format!("Ошибка создания временного файла: {}", e))?;
-        
-        use std::io::Write;
-        file.write_all(content.as_bytes())
-            .map_err(|e| format!("Ошибка записи во временный файл: {}", e))?;
-    }
-    
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    // Без проверки версии нечитаемый файл все равно можно перезаписать через sudo
+    let existing = match &expected_version {
+        Some(expected) => {
+            let existing = read_existing(&sftp, &file_path)?;
+            check_conflict(&file_path, existing.as_ref(), expected)?;
+            existing
+        }
+        None => read_existing(&sftp, &file_path).ok().flatten(),
+    };
 
-    let escaped_path = file_path.replace("'", "'\"'\"'");
-    let escaped_temp = temp_file.replace("'", "'\"'\"'");
-    
-    let command = format!("cp '{}' '{}' && rm '{}'", escaped_temp, escaped_path, escaped_temp);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды: {}", e))?;
+    let text_format = text_format
+        .or_else(|| existing.as_ref().map(|existing| TextFormat::detect(&existing.content)))
+        .unwrap_or_default();
+    let bytes = text_format.encode(&content)?;
 
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
+    let original = sftp.stat(Path::new(&file_path)).ok();
+    let attributes = FileAttributes::of(original.as_ref());
 
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
+    let backup_path = match (&original, keep_backup.unwrap_or(false)) {
+        (Some(_), true) => Some(format!("{}.bak", file_path)),
+        _ => None,
+    };
 
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S cp '{}' '{}'",
-                connection_info.password, escaped_temp, escaped_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка сохранения файла с sudo (код {}): {}", sudo_exit_status, sudo_stderr));
+    let temp_path = sibling_temp_path(&file_path);
+    let replace = replace_command(&temp_path, &file_path, backup_path.as_deref());
+    let privileged_replace = attributes.restore_command(&temp_path).and(replace.clone());
+
+    let elevated = match create_remote_file(&sftp, &temp_path, attributes.mode) {
+        Ok(file) => {
+            write_remote_file(&sftp, file, &temp_path, &bytes)?;
+
+            // Чужого владельца без root не вернуть: тогда замена сразу идет через sudo
+            let output = if apply_attributes(&sftp, &temp_path, &attributes) {
+                Some(run_command(&sess, &replace)?)
+            } else {
+                None
+            };
+            let owner_lost = output.is_none();
+
+            match output {
+                Some(output) if output.success() => false,
+                Some(output) if !output.permission_denied() => {
+                    let _ = sftp.unlink(Path::new(&temp_path));
+                    return Err(output.error(Msg::CommandFailed.text()));
+                }
+                _ => {
+                    let sudo_output = run_as_admin(&sess, &connection_info, &privileged_replace);
+                    let chown_impossible = sudo_output.as_ref().is_ok_and(|output| chown_refused(&output.stderr));
+                    let may_drop_owner = owner_lost && (chown_impossible || drop_owner.unwrap_or(false));
+
+                    if sudo_output.as_ref().is_ok_and(|output| output.success()) {
+                        true
+                    } else if may_drop_owner && run_command(&sess, &replace).is_ok_and(|output| output.success()) {
+                        false
+                    } else {
+                        let cleanup = RemoteCommand::new("rm").arg("-f").arg("--").arg(&temp_path);
+                        let _ = run_as_admin(&sess, &connection_info, &cleanup);
+
+                        let mut error = match sudo_output {
+                            Ok(output) => output.error(Msg::SudoSaveFailed.text()),
+                            Err(error) => error,
+                        };
+
+                        // Редактор может повторить сохранение с `drop_owner`, если пользователь согласен сменить владельца
+                        if owner_lost {
+                            error.details["owner_droppable"] = json!(true);
+                        }
+
+                        return Err(error);
+                    }
+                }
             }
-            
-            let mut cleanup_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для очистки: {}", e))?;
-            
-            let cleanup_command = format!("rm '{}'", escaped_temp);
-            let _ = cleanup_channel.exec(&cleanup_command);
-            let _ = cleanup_channel.wait_close();
-            
-            return Ok("Файл успешно сохранен с правами администратора".to_string());
         }
-        
-        return Err(format!("Команда завершилась с ошибкой (код {}): {}", exit_status, stderr));
-    }
+        Err(e) if is_sftp_permission_denied(&e) => {
+            // Каталог закрыт на запись: содержимое кладется в /tmp, а sudo переносит его
+            // во временный файл рядом с исходным и уже оттуда переименовывает
+            let staging_path = format!("/tmp/ssh_editor_{}", unique_suffix());
+
+            let staging = create_remote_file(&sftp, &staging_path, 0o600)
+                .map_err(|e| AppError::ssh(Msg::TempFileCreateFailed.text(), &e))?;
+            write_remote_file(&sftp, staging, &staging_path, &bytes)?;
+
+            let command = RemoteCommand::new("cp").arg("--").arg(&staging_path).arg(&temp_path)
+                .and(privileged_replace);
+
+            let sudo_output = run_as_admin(&sess, &connection_info, &command);
+            let _ = sftp.unlink(Path::new(&staging_path));
+            let sudo_output = sudo_output?;
+
+            if !sudo_output.success() {
+                let cleanup = RemoteCommand::new("rm").arg("-f").arg("--").arg(&temp_path);
+                let _ = run_as_admin(&sess, &connection_info, &cleanup);
+
+                return Err(sudo_output.error(Msg::SudoSaveFailed.text()));
+            }
+
+            true
+        }
+        Err(e) => return Err(AppError::ssh(Msg::TempFileCreateFailed.text(), &e)),
+    };
+
+    let mtime = sftp.stat(Path::new(&file_path)).ok().and_then(|stat| stat.mtime);
+    let preserved = PreservedAttributes::check(&sftp, &file_path, original.as_ref().map(|_| &attributes));
 
-    Ok("Файл успешно сохранен".to_string())
+    Ok(SaveResult {
+        message: Msg::FileSaved.elevated(elevated),
+        version: FileVersion::of(&bytes, mtime),
+        backup_path,
+        preserved,
+        text_format,
+    })
 }
+
+/// Можно ли открыть файл в текстовом редакторе. Тип определяется по первым килобайтам содержимого.
 #[command]
-pub fn check_file_permissions(file_path: String) -> Result<bool, String> {
-    let filename = file_path.split('/').last().unwrap_or(&file_path);
-    Ok(is_text_file(filename))
-}
\ No newline at end of file
+pub fn check_file_permissions(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, file_path: String) -> Result<bool, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let (_, _, content_type) = open_and_sniff(&sftp, &file_path)?;
+    Ok(content_type.is_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_file_stays_in_target_directory() {
+        assert!(sibling_temp_path("/etc/nginx/nginx.conf").starts_with("/etc/nginx/.nginx.conf.ssh-connect-"));
+        assert!(sibling_temp_path("/motd").starts_with("/.motd.ssh-connect-"));
+        assert!(sibling_temp_path("notes.txt").starts_with("./.notes.txt.ssh-connect-"));
+    }
+
+    #[test]
+    fn recognizes_refused_chown() {
+        assert!(chown_refused("chown: changing ownership of '/mnt/a': Operation not permitted\n"));
+        assert!(!chown_refused("sudo: a password is required\n"));
+        assert!(!chown_refused("mv: cannot move 'a' to 'b': Permission denied\n"));
+    }
+
+    #[test]
+    fn restores_owner_before_mode() {
+        let attributes = FileAttributes {
+            owner: Some((0, 33)),
+            mode: 0o4750,
+        };
+
+        assert_eq!(
+            attributes.restore_command("/etc/.x.tmp").as_str(),
+            "chown '0:33' '/etc/.x.tmp' && chmod '4750' '/etc/.x.tmp'"
+        );
+    }
+
+    #[test]
+    fn version_changes_with_content() {
+        let original = FileVersion::of(b"listen 80;\n", Some(1_700_000_000));
+        assert_eq!(original, FileVersion::of(b"listen 80;\n", Some(1_700_000_000)));
+        assert_ne!(original, FileVersion::of(b"listen 81;\n", Some(1_700_000_000)));
+        assert_eq!(content_hash(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+}
@@ -1,345 +1,522 @@
-use serde::{Deserialize, Serialize};
-use ssh2::Session;
-use std::io::Read;
-use std::net::TcpStream;
-use tauri::command;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SshConnectionInfo {
-    pub username: String,
-    pub host: String,
-    pub password: String,
-}
+use serde::Serialize;
+use ssh2::{Session, Sftp};
+use std::path::Path;
+use tauri::{command, State};
+
+use crate::connect_copy::OverwritePolicy;
+use crate::connection::{get_session, run_as_admin, run_with_sudo_fallback, SshConnectionInfo};
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::path_policy::{check_paths, PolicyOperation};
+use crate::remote_command::{run_command, CommandOutput, RemoteCommand};
+use crate::session_pool::SessionPool;
+use crate::trash::move_to_trash;
+
+/// Первый файл из папки `$1`, который уже есть в папке `$2`. Нужен политике `fail`
+/// при слиянии папок: код 1 и путь в stdout, если такой файл нашелся.
+const FIND_CONFLICT_SCRIPT: &str = r#"d=$(cd "$2" && pwd) && cd "$1" && find . ! -type d -exec sh -c 'for f; do if [ -e "$0/$f" ] || [ -L "$0/$f" ]; then printf "%s\n" "${f#./}"; exit 1; fi; done' "$d" {} +"#;
+
+/// Переносит из папки `$1` в папку `$2` только файлы, которых там еще нет, а затем
+/// удаляет опустевшие папки источника. Оставшиеся в `$1` файлы — пропущенные.
+const MOVE_MISSING_SCRIPT: &str = r#"d=$(cd "$2" && pwd) && (cd "$1" && find . ! -type d -exec sh -c 'for f; do [ -e "$0/$f" ] || [ -L "$0/$f" ] || { mkdir -p "$0/${f%/*}" && mv -- "$f" "$0/$f"; } || exit 1; done' "$d" {} +) && find "$1" -depth -type d -empty -exec rmdir -- {} +"#;
 
-fn create_ssh_session(connection_info: &SshConnectionInfo) -> Result<Session, String> {
-    let host_string = if connection_info.host.contains('@') {
-        connection_info.host.clone()
-    } else {
-        format!("{}@{}", connection_info.username, connection_info.host)
-    };
+#[command]
+pub fn create_file(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, file_path: String) -> Result<String, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
 
-    let parts: Vec<&str> = host_string.split('@').collect();
-    
-    if parts.len() != 2 {
-        return Err("Неверный формат строки подключения".to_string());
-    }
-    
-    let username = parts[0];
-    let host = parts[1];
+    let command = RemoteCommand::new("touch").arg(&file_path);
+
+    let elevated = run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoFileCreateFailed.text())?;
 
-    let tcp = TcpStream::connect(format!("{}:22", host))
-        .map_err(|e| format!("Ошибка подключения к серверу: {}", e))?;
+    Ok(Msg::FileCreated.elevated(elevated))
+}
 
-    let mut sess = Session::new()
-        .map_err(|e| format!("Ошибка создания SSH-сессии: {}", e))?;
+#[command]
+pub fn create_directory(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, dir_path: String) -> Result<String, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
 
-    sess.set_tcp_stream(tcp);
-    sess.handshake()
-        .map_err(|e| format!("Ошибка при рукопожатии SSH: {}", e))?;
+    let command = RemoteCommand::new("mkdir").arg("-p").arg(&dir_path);
 
-    sess.userauth_password(username, &connection_info.password)
-        .map_err(|e| format!("Ошибка аутентификации: {}", e))?;
+    let elevated = run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoFolderCreateFailed.text())?;
 
-    Ok(sess)
+    Ok(Msg::FolderCreated.elevated(elevated))
 }
 
 #[command]
-pub fn create_file(connection_info: SshConnectionInfo, file_path: String) -> Result<String, String> {
-    let sess = create_ssh_session(&connection_info)?;
-    
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-    let escaped_path = file_path.replace("'", "'\"'\"'");
-    let command = format!("touch '{}'", escaped_path);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды touch: {}", e))?;
-
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
-
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
-
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S touch '{}'",
-                connection_info.password, escaped_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка создания файла с sudo (код {}): {}", sudo_exit_status, sudo_stderr));
-            }
-            
-            return Ok("Файл успешно создан с правами администратора".to_string());
+pub fn delete_file(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, file_path: String, permanent: Option<bool>, confirmed: Option<bool>) -> Result<String, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    check_paths(&sess, &[(PolicyOperation::Delete, &file_path)], confirmed.unwrap_or(false))?;
+
+    if !permanent.unwrap_or(false) {
+        if let Some(elevated) = move_to_trash(&sess, &connection_info, &file_path, false)? {
+            return Ok(Msg::FileMovedToTrash.elevated(elevated));
         }
-        
-        return Err(format!("Команда завершилась с ошибкой (код {}): {}", exit_status, stderr));
     }
 
-    Ok("Файл успешно создан".to_string())
+    let command = RemoteCommand::new("rm").arg("--").arg(&file_path);
+
+    let elevated = run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoFileDeleteFailed.text())?;
+
+    Ok(Msg::FileDeleted.elevated(elevated))
 }
 
 #[command]
-pub fn create_directory(connection_info: SshConnectionInfo, dir_path: String) -> Result<String, String> {
-    let sess = create_ssh_session(&connection_info)?;
-    
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-    let escaped_path = dir_path.replace("'", "'\"'\"'");
-    let command = format!("mkdir -p '{}'", escaped_path);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды mkdir: {}", e))?;
-
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
-
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
-
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S mkdir -p '{}'",
-                connection_info.password, escaped_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка создания папки с sudo (код {}): {}", sudo_exit_status, sudo_stderr));
-            }
-            
-            return Ok("Папка успешно создана с правами администратора".to_string());
+pub fn delete_directory(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, dir_path: String, permanent: Option<bool>, confirmed: Option<bool>) -> Result<String, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    check_paths(&sess, &[(PolicyOperation::Delete, &dir_path)], confirmed.unwrap_or(false))?;
+
+    if !permanent.unwrap_or(false) {
+        if let Some(elevated) = move_to_trash(&sess, &connection_info, &dir_path, true)? {
+            return Ok(Msg::FolderMovedToTrash.elevated(elevated));
         }
-        
-        return Err(format!("Команда завершилась с ошибкой (код {}): {}", exit_status, stderr));
     }
 
-    Ok("Папка успешно создана".to_string())
+    let command = RemoteCommand::new("rm").arg("-rf").arg("--").arg(&dir_path);
+
+    let elevated = run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoFolderDeleteFailed.text())?;
+
+    Ok(Msg::FolderDeleted.elevated(elevated))
 }
 
 #[command]
-pub fn delete_file(connection_info: SshConnectionInfo, file_path: String) -> Result<String, String> {
-    let sess = create_ssh_session(&connection_info)?;
-    
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-    let escaped_path = file_path.replace("'", "'\"'\"'");
-    let command = format!("rm '{}'", escaped_path);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды rm: {}", e))?;
-
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
-
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
-
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S rm '{}'",
-                connection_info.password, escaped_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка удаления файла с sudo (код {}): {}", sudo_exit_status, sudo_stderr));
+pub fn rename_file(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, old_path: String, new_path: String, confirmed: Option<bool>) -> Result<String, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    check_paths(
+        &sess,
+        &[(PolicyOperation::Rename, &old_path), (PolicyOperation::Write, &new_path)],
+        confirmed.unwrap_or(false),
+    )?;
+
+    let command = RemoteCommand::new("mv").arg("--").arg(&old_path).arg(&new_path);
+
+    let elevated = run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoRenameFailed.text())?;
+
+    Ok(Msg::Renamed.elevated(elevated))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathOperation {
+    Copy,
+    Move,
+}
+
+/// Что делать с путем на сервере.
+#[derive(Debug, PartialEq)]
+enum PathPlan {
+    /// Назначение существует, а политика `skip`
+    Skip,
+    Run {
+        /// Для политики `fail` при слиянии папок: сначала убедиться, что файлы не пересекаются
+        conflict_check: Option<RemoteCommand>,
+        command: RemoteCommand,
+    },
+}
+
+/// `Some(true)` для папки, `Some(false)` для файла или ссылки, `None`, если пути нет.
+fn path_kind(sftp: &Sftp, path: &str) -> Option<bool> {
+    sftp.stat(Path::new(path)).ok().map(|stat| stat.is_dir())
+}
+
+fn script(script: &'static str, source: &str, destination: &str) -> RemoteCommand {
+    RemoteCommand::new("sh").arg("-c").arg(script).arg("sh").arg(source).arg(destination)
+}
+
+/// Строит команду копирования или перемещения. Атрибуты сохраняет `cp -a` (или сам `mv`).
+/// Папка, которая уже есть в назначении, объединяется с источником, а политика применяется
+/// к каждому файлу внутри.
+fn plan_path_operation(
+    operation: PathOperation,
+    source: &str,
+    source_is_dir: bool,
+    destination: &str,
+    destination_is_dir: Option<bool>,
+    policy: OverwritePolicy,
+) -> Result<PathPlan, AppError> {
+    let Some(destination_is_dir) = destination_is_dir else {
+        let command = match operation {
+            PathOperation::Copy => RemoteCommand::new("cp").arg("-a").arg("--").arg(source).arg(destination),
+            PathOperation::Move => RemoteCommand::new("mv").arg("--").arg(source).arg(destination),
+        };
+
+        return Ok(PathPlan::Run {
+            conflict_check: None,
+            command,
+        });
+    };
+
+    match (source_is_dir, destination_is_dir) {
+        (true, false) => return Err(AppError::conflict(Msg::CannotReplaceFileWithFolder.format(&[&destination]))),
+        (false, true) => return Err(AppError::conflict(Msg::DestinationIsFolder.format(&[&destination]))),
+        _ => {}
+    }
+
+    if !source_is_dir {
+        if !policy.should_copy(true, destination)? {
+            return Ok(PathPlan::Skip);
+        }
+
+        let command = match operation {
+            PathOperation::Copy => RemoteCommand::new("cp").arg("-a").arg("-f").arg("--").arg(source).arg(destination),
+            PathOperation::Move => RemoteCommand::new("mv").arg("-f").arg("--").arg(source).arg(destination),
+        };
+
+        return Ok(PathPlan::Run {
+            conflict_check: None,
+            command,
+        });
+    }
+
+    let contents = format!("{}/.", source.trim_end_matches('/'));
+    let merge = |flag: &'static str| RemoteCommand::new("cp").arg("-a").arg(flag).arg("--").arg(&contents).arg(destination);
+    let remove_source = || RemoteCommand::new("rm").arg("-rf").arg("--").arg(source);
+
+    let command = match (operation, policy) {
+        (PathOperation::Copy, OverwritePolicy::Skip) => merge("-n"),
+        (PathOperation::Copy, _) => merge("-f"),
+        (PathOperation::Move, OverwritePolicy::Skip) => script(MOVE_MISSING_SCRIPT, source, destination),
+        (PathOperation::Move, _) => merge("-f").and(remove_source()),
+    };
+
+    let conflict_check = (policy == OverwritePolicy::Fail).then(|| script(FIND_CONFLICT_SCRIPT, source, destination));
+
+    Ok(PathPlan::Run {
+        conflict_check,
+        command,
+    })
+}
+
+/// Путь, который напечатал `FIND_CONFLICT_SCRIPT`, или пустая строка.
+fn first_conflict(output: &CommandOutput) -> &str {
+    output.stdout.lines().next().unwrap_or("").trim()
+}
+
+fn run_path_operation(
+    pool: &SessionPool,
+    connection_info: &SshConnectionInfo,
+    operation: PathOperation,
+    source_path: &str,
+    destination_path: &str,
+    policy: OverwritePolicy,
+    confirmed: bool,
+) -> Result<String, AppError> {
+    let sess = get_session(pool, connection_info)?;
+
+    let checks = match operation {
+        PathOperation::Copy => vec![(PolicyOperation::Write, destination_path)],
+        PathOperation::Move => vec![(PolicyOperation::Rename, source_path), (PolicyOperation::Write, destination_path)],
+    };
+
+    check_paths(&sess, &checks, confirmed)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let source_is_dir = sftp.lstat(Path::new(source_path))
+        .map_err(|e| AppError::ssh(&Msg::SourceUnavailable.format(&[&source_path]), &e))?
+        .is_dir();
+
+    let plan = plan_path_operation(operation, source_path, source_is_dir, destination_path, path_kind(&sftp, destination_path), policy)?;
+
+    let PathPlan::Run { conflict_check, command } = plan else {
+        return Ok(Msg::PathSkipped.format(&[&destination_path]));
+    };
+
+    if let Some(conflict_check) = conflict_check {
+        let mut output = run_command(&sess, &conflict_check)?;
+
+        // Нечитаемые папки проверяются через sudo, как и сама операция
+        if !output.success() && first_conflict(&output).is_empty() && output.permission_denied() {
+            output = run_as_admin(&sess, connection_info, &conflict_check)?;
+        }
+
+        // Без успешной проверки операция с политикой `fail` может перезаписать файлы
+        if !output.success() {
+            let conflict = first_conflict(&output);
+
+            if conflict.is_empty() {
+                return Err(output.error(&Msg::ConflictCheckFailed.format(&[&destination_path])));
             }
-            
-            return Ok("Файл успешно удален с правами администратора".to_string());
+
+            return Err(AppError::conflict(Msg::FileExists.format(&[&format!("{}/{}", destination_path.trim_end_matches('/'), conflict)])));
         }
-        
-        return Err(format!("Команда завершилась с ошибкой (код {}): {}", exit_status, stderr));
     }
 
-    Ok("Файл успешно удален".to_string())
+    let (sudo_error, message) = match operation {
+        PathOperation::Copy => (Msg::SudoCopyFailed, Msg::Copied),
+        PathOperation::Move => (Msg::SudoMoveFailed, Msg::Moved),
+    };
+
+    let elevated = run_with_sudo_fallback(&sess, connection_info, &command, sudo_error.text())?;
+
+    Ok(message.elevated(elevated))
+}
+
+/// Копирует файл или папку в пределах сервера, не передавая данные через компьютер.
+#[command]
+pub fn copy_path(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    source_path: String,
+    destination_path: String,
+    overwrite_policy: Option<OverwritePolicy>,
+    confirmed: Option<bool>,
+) -> Result<String, AppError> {
+    run_path_operation(&pool, &connection_info, PathOperation::Copy, &source_path, &destination_path, overwrite_policy.unwrap_or_default(), confirmed.unwrap_or(false))
 }
 
+/// Перемещает файл или папку в пределах сервера.
 #[command]
-pub fn delete_directory(connection_info: SshConnectionInfo, dir_path: String) -> Result<String, String> {
-    let sess = create_ssh_session(&connection_info)?;
-    
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-    let escaped_path = dir_path.replace("'", "'\"'\"'");
-    let command = format!("rm -rf '{}'", escaped_path);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды rm -rf: {}", e))?;
-
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
-
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
-
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S rm -rf '{}'",
-                connection_info.password, escaped_path
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка удаления папки с sudo (код {}): {}", sudo_exit_status, sudo_stderr));
+pub fn move_path(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    source_path: String,
+    destination_path: String,
+    overwrite_policy: Option<OverwritePolicy>,
+    confirmed: Option<bool>,
+) -> Result<String, AppError> {
+    run_path_operation(&pool, &connection_info, PathOperation::Move, &source_path, &destination_path, overwrite_policy.unwrap_or_default(), confirmed.unwrap_or(false))
+}
+
+/// Атрибуты пути после изменения прав или владельца.
+#[derive(Debug, Serialize)]
+pub struct PathAttributes {
+    pub path: String,
+    /// Права в восьмеричном виде: `755`, `4755`
+    pub mode: String,
+    /// Права как в `ls -l`: `drwxr-xr-x`
+    pub permissions: String,
+    pub owner: String,
+    pub group: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub modified: u64,
+}
+
+fn parse_path_attributes(path: &str, stat_output: &str) -> Option<PathAttributes> {
+    let parts: Vec<&str> = stat_output.trim().splitn(8, '|').collect();
+    let [mode, permissions, owner, group, uid, gid, size, modified] = parts.as_slice() else {
+        return None;
+    };
+
+    Some(PathAttributes {
+        path: path.to_string(),
+        mode: mode.to_string(),
+        permissions: permissions.to_string(),
+        owner: owner.to_string(),
+        group: group.to_string(),
+        uid: uid.parse().ok()?,
+        gid: gid.parse().ok()?,
+        size: size.parse().ok()?,
+        modified: modified.parse().ok()?,
+    })
+}
+
+fn read_path_attributes(sess: &Session, path: &str) -> Result<PathAttributes, AppError> {
+    let command = RemoteCommand::new("stat").arg("-c").arg("%a|%A|%U|%G|%u|%g|%s|%Y").arg("--").arg(path);
+    let output = run_command(sess, &command)?;
+
+    if !output.success() {
+        return Err(output.error(&Msg::AttributesReadFailed.format(&[&path])));
+    }
+
+    parse_path_attributes(path, &output.stdout)
+        .ok_or_else(|| AppError::internal(Msg::AttributesParseFailed.format(&[&path])))
+}
+
+/// Восьмеричные права (`644`, `2775`) или символьные в синтаксисе chmod (`u+x,go-w`, `a=rX`).
+fn validate_mode(mode: &str) -> Result<(), AppError> {
+    let is_octal = (3..=4).contains(&mode.len()) && mode.chars().all(|c| ('0'..='7').contains(&c));
+
+    let is_symbolic = !mode.is_empty() && mode.split(',').all(|clause| {
+        let actions = clause.trim_start_matches(['u', 'g', 'o', 'a']);
+        actions.starts_with(['+', '-', '='])
+            && actions.chars().all(|c| "+-=rwxXstugo".contains(c))
+    });
+
+    if is_octal || is_symbolic {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(Msg::InvalidMode.format(&[&mode])))
+    }
+}
+
+/// Имя пользователя или группы либо числовой ID.
+fn validate_account(name: &str, invalid: Msg) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '$'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(invalid.format(&[&name])))
+    }
+}
+
+/// Владелец в синтаксисе chown; в `user:group` одна из частей может быть пустой, но не обе.
+fn validate_owner(owner: &str) -> Result<(), AppError> {
+    match owner.split_once(':') {
+        Some(("", "")) => Err(AppError::invalid_input(Msg::InvalidUserName.format(&[&owner]))),
+        Some((user, group)) => {
+            if !user.is_empty() {
+                validate_account(user, Msg::InvalidUserName)?;
             }
-            
-            return Ok("Папка успешно удалена с правами администратора".to_string());
+            if !group.is_empty() {
+                validate_account(group, Msg::InvalidGroupName)?;
+            }
+            Ok(())
         }
-        
-        return Err(format!("Команда завершилась с ошибкой (код {}): {}", exit_status, stderr));
+        None => validate_account(owner, Msg::InvalidUserName),
     }
+}
 
-    Ok("Папка успешно удалена".to_string())
+/// `program [-R] -- value path`
+fn attribute_command(program: &'static str, value: &str, path: &str, recursive: bool) -> RemoteCommand {
+    let command = RemoteCommand::new(program);
+    let command = if recursive { command.arg("-R") } else { command };
+    command.arg("--").arg(value).arg(path)
 }
 
+fn change_attribute(
+    pool: &SessionPool,
+    connection_info: &SshConnectionInfo,
+    command: &RemoteCommand,
+    path: &str,
+    sudo_error: &str,
+) -> Result<PathAttributes, AppError> {
+    let sess = get_session(pool, connection_info)?;
+
+    run_with_sudo_fallback(&sess, connection_info, command, sudo_error)?;
+
+    read_path_attributes(&sess, path)
+}
+
+/// Меняет права доступа (chmod). Возвращает атрибуты пути после изменения.
 #[command]
-pub fn rename_file(connection_info: SshConnectionInfo, old_path: String, new_path: String) -> Result<String, String> {
-    let sess = create_ssh_session(&connection_info)?;
-    
-    let mut channel = sess.channel_session()
-        .map_err(|e| format!("Ошибка создания канала: {}", e))?;
-
-    let escaped_old = old_path.replace("'", "'\"'\"'");
-    let escaped_new = new_path.replace("'", "'\"'\"'");
-    let command = format!("mv '{}' '{}'", escaped_old, escaped_new);
-    
-    channel.exec(&command)
-        .map_err(|e| format!("Ошибка выполнения команды mv: {}", e))?;
-
-    let mut output = String::new();
-    let mut stderr = String::new();
-    
-    if let Ok(_) = channel.read_to_string(&mut output) {}
-    if let Ok(_) = channel.stderr().read_to_string(&mut stderr) {}
-
-    channel.wait_close()
-        .map_err(|e| format!("Ошибка закрытия канала: {}", e))?;
-
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        if stderr.contains("Permission denied") || stderr.contains("permission denied") {
-            let mut sudo_channel = sess.channel_session()
-                .map_err(|e| format!("Ошибка создания канала для sudo: {}", e))?;
-            
-            let sudo_command = format!(
-                "echo '{}' | sudo -S mv '{}' '{}'",
-                connection_info.password, escaped_old, escaped_new
-            );
-            
-            sudo_channel.exec(&sudo_command)
-                .map_err(|e| format!("Ошибка выполнения sudo команды: {}", e))?;
-            
-            let mut sudo_output = String::new();
-            let mut sudo_stderr = String::new();
-            if let Ok(_) = sudo_channel.read_to_string(&mut sudo_output) {}
-            if let Ok(_) = sudo_channel.stderr().read_to_string(&mut sudo_stderr) {}
-            
-            sudo_channel.wait_close()
-                .map_err(|e| format!("Ошибка закрытия sudo канала: {}", e))?;
-            
-            let sudo_exit_status = sudo_channel.exit_status().unwrap_or(-1);
-            
-            if sudo_exit_status != 0 {
-                return Err(format!("Ошибка переименования с sudo (код {}): {}", sudo_exit_status, sudo_stderr));
-            }
-            
-            return Ok("Переименование выполнено успешно с правами администратора".to_string());
+pub fn change_mode(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    path: String,
+    mode: String,
+    recursive: Option<bool>,
+) -> Result<PathAttributes, AppError> {
+    validate_mode(&mode)?;
+
+    let command = attribute_command("chmod", &mode, &path, recursive.unwrap_or(false));
+    change_attribute(&pool, &connection_info, &command, &path, Msg::SudoChmodFailed.text())
+}
+
+/// Меняет владельца (chown). `owner` может быть в виде `user`, `user:group`,
+/// `user:` (группа — основная группа пользователя) или `:group` (только группа).
+#[command]
+pub fn change_owner(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    path: String,
+    owner: String,
+    recursive: Option<bool>,
+) -> Result<PathAttributes, AppError> {
+    validate_owner(&owner)?;
+
+    let command = attribute_command("chown", &owner, &path, recursive.unwrap_or(false));
+    change_attribute(&pool, &connection_info, &command, &path, Msg::SudoChownFailed.text())
+}
+
+/// Меняет группу (chgrp).
+#[command]
+pub fn change_group(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    path: String,
+    group: String,
+    recursive: Option<bool>,
+) -> Result<PathAttributes, AppError> {
+    validate_account(&group, Msg::InvalidGroupName)?;
+
+    let command = attribute_command("chgrp", &group, &path, recursive.unwrap_or(false));
+    change_attribute(&pool, &connection_info, &command, &path, Msg::SudoChgrpFailed.text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(plan: PathPlan) -> String {
+        match plan {
+            PathPlan::Run { command, .. } => command.as_str().to_string(),
+            PathPlan::Skip => panic!("ожидалась команда"),
         }
-        
-        return Err(format!("Команда завершилась с ошибкой (код {}): {}", exit_status, stderr));
     }
 
-    Ok("Переименование выполнено успешно".to_string())
-}
\ No newline at end of file
+    #[test]
+    fn copies_and_moves_to_new_path() {
+        let copy = plan_path_operation(PathOperation::Copy, "/srv/app", true, "/srv/app2", None, OverwritePolicy::Fail).unwrap();
+        assert_eq!(command(copy), "cp '-a' '--' '/srv/app' '/srv/app2'");
+
+        let rename = plan_path_operation(PathOperation::Move, "/a.txt", false, "/b.txt", None, OverwritePolicy::Fail).unwrap();
+        assert_eq!(command(rename), "mv '--' '/a.txt' '/b.txt'");
+    }
+
+    #[test]
+    fn applies_policy_to_existing_file() {
+        assert_eq!(
+            plan_path_operation(PathOperation::Copy, "/a", false, "/b", Some(false), OverwritePolicy::Skip).unwrap(),
+            PathPlan::Skip
+        );
+        assert!(plan_path_operation(PathOperation::Move, "/a", false, "/b", Some(false), OverwritePolicy::Fail).is_err());
+        assert!(plan_path_operation(PathOperation::Copy, "/a", true, "/b", Some(false), OverwritePolicy::Overwrite).is_err());
+    }
+
+    #[test]
+    fn merges_existing_directories() {
+        let merge = plan_path_operation(PathOperation::Move, "/srv/new/", true, "/srv/app", Some(true), OverwritePolicy::Fail).unwrap();
+
+        let PathPlan::Run { conflict_check, command } = merge else {
+            panic!("ожидалась команда");
+        };
+        assert!(conflict_check.is_some());
+        assert_eq!(
+            command.as_str(),
+            "cp '-a' '-f' '--' '/srv/new/.' '/srv/app' && rm '-rf' '--' '/srv/new/'"
+        );
+    }
+
+    #[test]
+    fn validates_modes() {
+        for mode in ["644", "2775", "u+x", "go-w", "a=rX,u+s", "-w", "+t"] {
+            assert!(validate_mode(mode).is_ok(), "{}", mode);
+        }
+
+        for mode in ["", "8", "99999", "u", "x+u", "u+q", "644; reboot"] {
+            assert!(validate_mode(mode).is_err(), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn validates_owners() {
+        for owner in ["deploy", "deploy:www-data", "deploy:", ":www-data", "1001:33"] {
+            assert!(validate_owner(owner).is_ok(), "{}", owner);
+        }
+
+        for owner in ["", ":", "-R", "deploy:-x", "a b:c"] {
+            assert!(validate_owner(owner).is_err(), "{}", owner);
+        }
+    }
+
+    #[test]
+    fn parses_stat_output() {
+        let attributes = parse_path_attributes("/srv/app", "2775|drwxrwsr-x|deploy|www-data|1001|33|4096|1700000000\n").unwrap();
+
+        assert_eq!(attributes.mode, "2775");
+        assert_eq!(attributes.permissions, "drwxrwsr-x");
+        assert_eq!((attributes.owner.as_str(), attributes.gid), ("deploy", 33));
+        assert_eq!(
+            attribute_command("chmod", "-w", "/srv/app", true).as_str(),
+            "chmod '-R' '--' '-w' '/srv/app'"
+        );
+    }
+}
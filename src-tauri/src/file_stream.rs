@@ -0,0 +1,523 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use ssh2::Sftp;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::connection::{get_session, SshConnectionInfo};
+use crate::session_pool::SessionPool;
+use crate::terminal::take_complete_utf8;
+
+/// Больше этого за один запрос не отдается: крупные файлы читаются постранично.
+const MAX_RANGE_LENGTH: u64 = 4 * 1024 * 1024;
+const READ_BLOCK_SIZE: usize = 64 * 1024;
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Предел паузы между попытками открыть файл, пропавший при ротации.
+const TAIL_MAX_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// Сколько новых данных отправляется одним событием `file-tail-<id>`.
+const MAX_TAIL_EVENT_LENGTH: u64 = 256 * 1024;
+/// Размер страницы шестнадцатеричного просмотра и ширина его строки.
+const MAX_HEX_PAGE_LENGTH: u64 = 64 * 1024;
+const HEX_ROW_WIDTH: usize = 16;
+
+/// Какую часть файла прочитать.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReadRange {
+    /// `length` байт начиная с `offset`
+    Bytes { offset: u64, length: u64 },
+    /// `line_count` строк начиная со строки `start_line` (с нуля)
+    Lines { start_line: u64, line_count: u64 },
+    /// Последние `line_count` строк
+    LastLines { line_count: u64 },
+}
+
+/// Прочитанный фрагмент файла. `offset + length` — начало следующей страницы.
+#[derive(Debug, Serialize)]
+pub struct FileChunk {
+    pub content: String,
+    pub offset: u64,
+    pub length: u64,
+    pub file_size: u64,
+    /// Номер первой строки фрагмента, если он известен
+    pub first_line: Option<u64>,
+    pub eof: bool,
+}
+
+/// В каком виде отдавать байты для шестнадцатеричного просмотра.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HexFormat {
+    /// Готовые строки дампа: смещение, байты в hex и их ASCII-представление
+    #[default]
+    Rows,
+    /// Сырые байты в base64, разбор остается фронтенду
+    Base64,
+}
+
+/// Строка дампа, как у `hexdump -C`: до 16 байт.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HexRow {
+    pub offset: u64,
+    /// Байты через пробел: `7f 45 4c 46`
+    pub hex: String,
+    /// Печатные ASCII-символы, остальные заменены точкой
+    pub ascii: String,
+}
+
+/// Страница шестнадцатеричного просмотра. Заполнено `rows` или `base64` — в зависимости от формата.
+#[derive(Debug, Serialize)]
+pub struct HexPage {
+    pub offset: u64,
+    pub length: u64,
+    pub file_size: u64,
+    pub eof: bool,
+    pub rows: Option<Vec<HexRow>>,
+    pub base64: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct TailData {
+    tail_id: String,
+    data: String,
+    offset: u64,
+    /// Файл был обрезан или заменен, чтение началось сначала
+    truncated: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct TailStopped {
+    tail_id: String,
+    /// Ошибка, из-за которой наблюдение прекратилось само
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<AppError>,
+}
+
+/// Открытые `tail -f`: у каждого свой поток, который останавливается по флагу.
+#[derive(Default)]
+pub struct TailManager {
+    tails: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl TailManager {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.tails.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn data_event(tail_id: &str) -> String {
+    format!("file-tail-{}", tail_id)
+}
+
+fn stopped_event(tail_id: &str) -> String {
+    format!("file-tail-stopped-{}", tail_id)
+}
+
+fn open_file(sftp: &Sftp, file_path: &str) -> Result<(ssh2::File, u64), AppError> {
+    let mut file = sftp.open(Path::new(file_path))
+        .map_err(|e| AppError::ssh(Msg::FileOpenFailed.text(), &e))?;
+
+    let size = file.stat()
+        .map_err(|e| AppError::ssh(Msg::FileStatFailed.text(), &e))?
+        .size
+        .unwrap_or(0);
+
+    Ok((file, size))
+}
+
+fn read_exact_range(file: &mut (impl Read + Seek), offset: u64, length: u64) -> Result<Vec<u8>, AppError> {
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| AppError::io(&Msg::SeekFailed.format(&[&offset]), &e))?;
+
+    let mut buffer = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut buffer)
+        .map_err(|e| AppError::io(Msg::FileReadFailed.text(), &e))?;
+
+    Ok(buffer)
+}
+
+/// Смещение начала строки `line` (с нуля) или `None`, если в файле меньше строк.
+fn line_offset(file: &mut (impl Read + Seek), line: u64) -> Result<Option<u64>, AppError> {
+    if line == 0 {
+        return Ok(Some(0));
+    }
+
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| AppError::io(Msg::SeekStartFailed.text(), &e))?;
+
+    let mut buffer = vec![0u8; READ_BLOCK_SIZE];
+    let mut position = 0u64;
+    let mut lines = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| AppError::io(Msg::FileReadFailed.text(), &e))?;
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        for (index, byte) in buffer[..read].iter().enumerate() {
+            if *byte == b'\n' {
+                lines += 1;
+                if lines == line {
+                    return Ok(Some(position + index as u64 + 1));
+                }
+            }
+        }
+
+        position += read as u64;
+    }
+}
+
+/// Длина `line_count` строк начиная с `offset`, но не больше `MAX_RANGE_LENGTH`.
+fn lines_length(file: &mut (impl Read + Seek), offset: u64, line_count: u64) -> Result<u64, AppError> {
+    if line_count == 0 {
+        return Ok(0);
+    }
+
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| AppError::io(&Msg::SeekFailed.format(&[&offset]), &e))?;
+
+    let mut buffer = vec![0u8; READ_BLOCK_SIZE];
+    let mut length = 0u64;
+    let mut lines = 0u64;
+
+    while length < MAX_RANGE_LENGTH {
+        let read = file.read(&mut buffer)
+            .map_err(|e| AppError::io(Msg::FileReadFailed.text(), &e))?;
+
+        if read == 0 {
+            break;
+        }
+
+        for (index, byte) in buffer[..read].iter().enumerate() {
+            if *byte == b'\n' {
+                lines += 1;
+                if lines == line_count {
+                    return Ok((length + index as u64 + 1).min(MAX_RANGE_LENGTH));
+                }
+            }
+        }
+
+        length += read as u64;
+    }
+
+    Ok(length.min(MAX_RANGE_LENGTH))
+}
+
+/// Смещение начала последних `line_count` строк. Файл читается блоками с конца;
+/// перевод строки в самом конце файла не считается началом новой строки.
+fn last_lines_offset(file: &mut (impl Read + Seek), size: u64, line_count: u64) -> Result<u64, AppError> {
+    if line_count == 0 {
+        return Ok(size);
+    }
+
+    let mut buffer = vec![0u8; READ_BLOCK_SIZE];
+    let mut end = size;
+    let mut lines = 0u64;
+
+    while end > 0 {
+        let start = end.saturating_sub(READ_BLOCK_SIZE as u64);
+        let block = &mut buffer[..(end - start) as usize];
+
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(block))
+            .map_err(|e| AppError::io(Msg::FileReadFailed.text(), &e))?;
+
+        for (index, byte) in block.iter().enumerate().rev() {
+            let position = start + index as u64;
+
+            if *byte == b'\n' && position + 1 != size {
+                lines += 1;
+                if lines == line_count {
+                    return Ok(position + 1);
+                }
+            }
+        }
+
+        end = start;
+    }
+
+    Ok(0)
+}
+
+/// Читает часть файла через SFTP с переходом к нужной позиции, не загружая файл целиком.
+/// Байтовые диапазоны могут разрезать многобайтовый символ: такие байты заменяются на U+FFFD.
+#[command]
+pub fn read_file_range(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, file_path: String, range: ReadRange) -> Result<FileChunk, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let (mut file, file_size) = open_file(&sftp, &file_path)?;
+
+    let (offset, length, first_line) = match range {
+        ReadRange::Bytes { offset, length } => {
+            let offset = offset.min(file_size);
+            (offset, length.min(MAX_RANGE_LENGTH).min(file_size - offset), None)
+        }
+        ReadRange::Lines { start_line, line_count } => match line_offset(&mut file, start_line)? {
+            Some(offset) => (offset, lines_length(&mut file, offset, line_count)?, Some(start_line)),
+            None => (file_size, 0, None),
+        },
+        ReadRange::LastLines { line_count } => {
+            let offset = last_lines_offset(&mut file, file_size, line_count)?;
+            (offset, (file_size - offset).min(MAX_RANGE_LENGTH), None)
+        }
+    };
+
+    let content = read_exact_range(&mut file, offset, length)?;
+    let length = content.len() as u64;
+
+    Ok(FileChunk {
+        content: String::from_utf8_lossy(&content).into_owned(),
+        offset,
+        length,
+        file_size,
+        first_line,
+        eof: offset + length >= file_size,
+    })
+}
+
+fn hex_rows(bytes: &[u8], offset: u64) -> Vec<HexRow> {
+    bytes
+        .chunks(HEX_ROW_WIDTH)
+        .enumerate()
+        .map(|(index, row)| HexRow {
+            offset: offset + (index * HEX_ROW_WIDTH) as u64,
+            hex: row.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" "),
+            ascii: row
+                .iter()
+                .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Постраничный шестнадцатеричный просмотр: `length` байт начиная с `offset`,
+/// не больше 64 КБ за раз. Подходит для любых файлов, в том числе бинарных.
+#[command]
+pub fn read_file_hex(
+    pool: State<'_, SessionPool>,
+    connection_info: SshConnectionInfo,
+    file_path: String,
+    offset: u64,
+    length: u64,
+    format: Option<HexFormat>,
+) -> Result<HexPage, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let (mut file, file_size) = open_file(&sftp, &file_path)?;
+
+    let offset = offset.min(file_size);
+    let bytes = read_exact_range(&mut file, offset, length.min(MAX_HEX_PAGE_LENGTH))?;
+    let length = bytes.len() as u64;
+
+    let (rows, base64) = match format.unwrap_or_default() {
+        HexFormat::Rows => (Some(hex_rows(&bytes, offset)), None),
+        HexFormat::Base64 => (None, Some(STANDARD.encode(&bytes))),
+    };
+
+    Ok(HexPage {
+        offset,
+        length,
+        file_size,
+        eof: offset + length >= file_size,
+        rows,
+        base64,
+    })
+}
+
+/// Следит за файлом как `tail -F`: новые данные приходят событиями `file-tail-<id>`,
+/// остановка — событием `file-tail-stopped-<id>`, в котором есть `error`, если наблюдение
+/// прервала ошибка. Id задает фронтенд, чтобы подписаться на события до запуска.
+/// Если файл обрезали или заменили (ротация логов), чтение начинается с начала нового файла.
+/// `from_offset` по умолчанию — текущий конец файла; обычно это `offset + length` последней
+/// прочитанной страницы.
+#[command]
+pub fn start_file_tail(
+    app: AppHandle,
+    pool: State<'_, SessionPool>,
+    tails: State<'_, TailManager>,
+    connection_info: SshConnectionInfo,
+    tail_id: String,
+    file_path: String,
+    from_offset: Option<u64>,
+) -> Result<(), AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let (_, file_size) = open_file(&sftp, &file_path)?;
+    let offset = from_offset.unwrap_or(file_size).min(file_size);
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    {
+        let mut tails = tails.lock();
+        if tails.contains_key(&tail_id) {
+            return Err(AppError::conflict(Msg::TailIdInUse.format(&[&tail_id])));
+        }
+        tails.insert(tail_id.clone(), cancelled.clone());
+    }
+
+    thread::spawn(move || {
+        // Сессия остается выданной, пока идет наблюдение, и пул не закроет ее по простою
+        let _lease = sess;
+        let error = run_tail(&app, &tail_id, &sftp, &file_path, offset, &cancelled).err();
+
+        app.state::<TailManager>().lock().remove(&tail_id);
+
+        let _ = app.emit(
+            &stopped_event(&tail_id),
+            TailStopped {
+                tail_id: tail_id.clone(),
+                error,
+            },
+        );
+    });
+
+    Ok(())
+}
+
+#[command]
+pub fn stop_file_tail(tails: State<'_, TailManager>, tail_id: String) -> Result<(), AppError> {
+    let tails = tails.lock();
+    let cancelled = tails.get(&tail_id)
+        .ok_or_else(|| AppError::not_found(Msg::TailNotFound.format(&[&tail_id])))?;
+
+    cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Ждет `duration`, проверяя флаг отмены с шагом `TAIL_POLL_INTERVAL`.
+fn sleep_unless_cancelled(duration: Duration, cancelled: &AtomicBool) {
+    let mut remaining = duration;
+    while !remaining.is_zero() && !cancelled.load(Ordering::Relaxed) {
+        let step = remaining.min(TAIL_POLL_INTERVAL);
+        thread::sleep(step);
+        remaining -= step;
+    }
+}
+
+/// Читает дописанные с `offset` данные: не больше `MAX_TAIL_EVENT_LENGTH` за раз.
+/// Возвращает размер файла и прочитанные байты.
+fn read_appended(sftp: &Sftp, file_path: &str, offset: u64) -> Result<(u64, Vec<u8>), AppError> {
+    // Файл открывается заново на каждом шаге, чтобы после ротации читать уже новый файл
+    let (mut file, size) = open_file(sftp, file_path)?;
+
+    let start = if size < offset { 0 } else { offset };
+    let chunk = read_exact_range(&mut file, start, (size - start).min(MAX_TAIL_EVENT_LENGTH))?;
+
+    Ok((size, chunk))
+}
+
+/// Опрашивает размер файла и отправляет дописанные данные, пока наблюдение не остановят.
+/// Если файл пропал (ротация логов), попытки повторяются с растущей паузой, как у `tail -F`;
+/// на остальных ошибках наблюдение прекращается.
+fn run_tail(app: &AppHandle, tail_id: &str, sftp: &Sftp, file_path: &str, mut offset: u64, cancelled: &AtomicBool) -> Result<(), AppError> {
+    let event = data_event(tail_id);
+    // Хвост неполного UTF-8 символа, разрезанного между двумя событиями
+    let mut pending: Vec<u8> = Vec::new();
+    let mut retry_interval = TAIL_POLL_INTERVAL;
+
+    while !cancelled.load(Ordering::Relaxed) {
+        let (size, chunk) = match read_appended(sftp, file_path, offset) {
+            Ok(read) => read,
+            Err(error) if error.code == ErrorCode::NotFound => {
+                sleep_unless_cancelled(retry_interval, cancelled);
+                retry_interval = (retry_interval * 2).min(TAIL_MAX_RETRY_INTERVAL);
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        retry_interval = TAIL_POLL_INTERVAL;
+
+        let truncated = size < offset;
+        if truncated {
+            offset = 0;
+            pending.clear();
+        }
+
+        if chunk.is_empty() {
+            thread::sleep(TAIL_POLL_INTERVAL);
+            continue;
+        }
+
+        let chunk_offset = offset - pending.len() as u64;
+        offset += chunk.len() as u64;
+
+        pending.extend_from_slice(&chunk);
+        let data = take_complete_utf8(&mut pending);
+
+        if !data.is_empty() || truncated {
+            let _ = app.emit(
+                &event,
+                TailData {
+                    tail_id: tail_id.to_string(),
+                    data,
+                    offset: chunk_offset,
+                    truncated,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const LOG: &[u8] = b"first\nsecond\nthird\nfourth\n";
+
+    #[test]
+    fn finds_line_ranges() {
+        let mut file = Cursor::new(LOG);
+
+        let offset = line_offset(&mut file, 1).unwrap().unwrap();
+        assert_eq!(offset, 6);
+        assert_eq!(lines_length(&mut file, offset, 2).unwrap(), 13);
+        assert_eq!(lines_length(&mut file, offset, 0).unwrap(), 0);
+        assert_eq!(read_exact_range(&mut file, offset, 13).unwrap(), b"second\nthird\n");
+        assert_eq!(line_offset(&mut file, 10).unwrap(), None);
+    }
+
+    #[test]
+    fn formats_hex_rows() {
+        let rows = hex_rows(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0>\0", 4096);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].hex, "7f 45 4c 46 02 01 01 00 00 00 00 00 00 00 00 00");
+        assert_eq!(rows[0].ascii, ".ELF............");
+        assert_eq!(rows[1], HexRow { offset: 4112, hex: "3e 00".to_string(), ascii: ">.".to_string() });
+    }
+
+    #[test]
+    fn finds_last_lines_with_and_without_trailing_newline() {
+        let mut file = Cursor::new(LOG);
+        assert_eq!(last_lines_offset(&mut file, LOG.len() as u64, 2).unwrap(), 13);
+        assert_eq!(last_lines_offset(&mut file, LOG.len() as u64, 10).unwrap(), 0);
+
+        let unterminated = &LOG[..LOG.len() - 1];
+        let mut file = Cursor::new(unterminated);
+        assert_eq!(last_lines_offset(&mut file, unterminated.len() as u64, 1).unwrap(), 19);
+    }
+}
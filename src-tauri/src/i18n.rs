@@ -0,0 +1,416 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tauri::command;
+
+use crate::error::AppError;
+
+/// Язык сообщений, которые бэкенд возвращает интерфейсу.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Ru,
+    En,
+}
+
+const UNSET: u8 = 0;
+
+/// Выбранный язык; `UNSET`, пока язык не задан настройками и не определен по системе.
+static LANGUAGE: AtomicU8 = AtomicU8::new(UNSET);
+
+impl Language {
+    fn to_u8(self) -> u8 {
+        match self {
+            Language::Ru => 1,
+            Language::En => 2,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Language> {
+        match value {
+            1 => Some(Language::Ru),
+            2 => Some(Language::En),
+            _ => None,
+        }
+    }
+
+    /// Язык по локали вида `ru_RU.UTF-8` или `en-US`. `C` и `POSIX` языка не задают.
+    fn from_locale(locale: &str) -> Option<Language> {
+        let code = locale.split(['_', '-', '.', '@']).next()?.to_ascii_lowercase();
+
+        match code.as_str() {
+            "" | "c" | "posix" => None,
+            "ru" => Some(Language::Ru),
+            _ => Some(Language::En),
+        }
+    }
+}
+
+/// Язык системы по `LC_ALL`, `LC_MESSAGES` и `LANG`, а если они не заданы (Windows,
+/// запуск из Finder на macOS) — по языку из настроек ОС. Без локали — русский, как раньше.
+fn system_language() -> Language {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(sys_locale::get_locale())
+        .find_map(|locale| Language::from_locale(&locale))
+        .unwrap_or(Language::Ru)
+}
+
+/// Текущий язык сообщений.
+pub fn language() -> Language {
+    if let Some(language) = Language::from_u8(LANGUAGE.load(Ordering::Relaxed)) {
+        return language;
+    }
+
+    let language = system_language();
+    LANGUAGE.store(language.to_u8(), Ordering::Relaxed);
+    language
+}
+
+/// Применяет язык из настроек; `None` — язык системы.
+pub fn set_language(language: Option<Language>) {
+    let language = language.unwrap_or_else(system_language);
+    LANGUAGE.store(language.to_u8(), Ordering::Relaxed);
+}
+
+/// Объявляет каталог: ключ, русский и английский текст. `{}` — место для аргумента `Msg::format`.
+macro_rules! messages {
+    ($($key:ident => $ru:literal, $en:literal;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Msg {
+            $($key,)*
+        }
+
+        impl Msg {
+            #[cfg(test)]
+            const ALL: &'static [Msg] = &[$(Msg::$key,)*];
+
+            pub fn text_in(self, language: Language) -> &'static str {
+                match (self, language) {
+                    $(
+                        (Msg::$key, Language::Ru) => $ru,
+                        (Msg::$key, Language::En) => $en,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+messages! {
+    // Подключение и команды на сервере
+    ConnectFailed => "Ошибка подключения к серверу", "Failed to connect to the server";
+    SessionCreateFailed => "Ошибка создания SSH-сессии", "Failed to create an SSH session";
+    HandshakeFailed => "Ошибка при рукопожатии SSH", "SSH handshake failed";
+    Connected => "Успешное подключение", "Connected successfully";
+    SftpFailed => "Ошибка создания SFTP канала", "Failed to open an SFTP channel";
+    CommandFailed => "Команда завершилась с ошибкой", "Command failed";
+    CommandExitStatus => "{} (код {}): {}", "{} (exit code {}): {}";
+    AsAdministrator => "{} с правами администратора", "{} with administrator privileges";
+
+    // Конфигурация и настройки
+    HomeDirUnknown => "Не удалось определить домашнюю директорию", "Could not determine the home directory";
+    ConfigDirCreateFailed => "Ошибка создания директории {}", "Failed to create directory {}";
+    ConfigCreateFailed => "Ошибка создания файла конфигурации", "Failed to create the configuration file";
+    ConfigReadFailed => "Ошибка чтения файла конфигурации", "Failed to read the configuration file";
+    ConfigParseFailed => "Ошибка парсинга конфигурации", "Failed to parse the configuration";
+    ConfigSerializeFailed => "Ошибка сериализации конфигурации", "Failed to serialize the configuration";
+    ConfigWriteFailed => "Ошибка записи файла конфигурации", "Failed to write the configuration file";
+    ConfigBackupFailed => "Ошибка создания резервной копии конфигурации", "Failed to back up the configuration file";
+    ConfigRestoreFailed => "Ошибка восстановления конфигурации из резервной копии", "Failed to restore the configuration file from the backup";
+    SettingsReadFailed => "Ошибка чтения файла настроек", "Failed to read the settings file";
+    SettingsParseFailed => "Ошибка парсинга настроек", "Failed to parse the settings";
+    SettingsSerializeFailed => "Ошибка сериализации настроек", "Failed to serialize the settings";
+    SettingsWriteFailed => "Ошибка записи файла настроек", "Failed to write the settings file";
+    ServerNotFound => "Сервер с ID {} не найден", "Server with ID {} not found";
+    ServerRemoved => "Сервер с ID {} удален", "Server with ID {} removed";
+    InvalidTransferConcurrency => "Число одновременных передач должно быть больше нуля", "The number of concurrent transfers must be greater than zero";
+
+    // Просмотр и сохранение файлов
+    FileReadFailed => "Ошибка чтения файла", "Failed to read the file";
+    UnknownTarget => "неизвестно", "unknown";
+    SymlinkPreview => "Символическая ссылка\nПуть: {}\nСсылается на: {}", "Symbolic link\nPath: {}\nPoints to: {}";
+    LargeFilePreview => "Файл слишком большой для просмотра целиком, откройте его постранично\nРазмер: {} байт\nПуть: {}", "The file is too large to view at once, open it page by page\nSize: {} bytes\nPath: {}";
+    BinaryFilePreview => "Бинарный файл\nТип: {}\nПуть: {}\nСодержимое доступно в шестнадцатеричном просмотре", "Binary file\nType: {}\nPath: {}\nThe content is available in the hex viewer";
+    ConflictCheckReadFailed => "Ошибка чтения файла для проверки изменений", "Failed to read the file to check for changes";
+    FileChangedOnServer => "Файл '{}' был изменен на сервере после открытия", "File '{}' was changed on the server after it was opened";
+    FileDeletedOnServer => "Файл '{}' был удален на сервере после открытия", "File '{}' was deleted on the server after it was opened";
+    TempFileCreateFailed => "Ошибка создания временного файла", "Failed to create a temporary file";
+    TempFileWriteFailed => "Ошибка записи во временный файл", "Failed to write the temporary file";
+    FileNotEditable => "Этот тип файла нельзя редактировать", "This file type cannot be edited";
+    SudoSaveFailed => "Ошибка сохранения файла с sudo", "Failed to save the file with sudo";
+    FileSaved => "Файл успешно сохранен", "File saved successfully";
+    FileOpenFailed => "Ошибка открытия файла", "Failed to open the file";
+    FileStatFailed => "Ошибка получения информации о файле", "Failed to get file information";
+    SeekFailed => "Ошибка перехода к позиции {}", "Failed to seek to position {}";
+    SeekStartFailed => "Ошибка перехода к началу файла", "Failed to seek to the start of the file";
+    TailIdInUse => "Наблюдение {} уже запущено", "Tail {} is already running";
+    TailNotFound => "Наблюдение {} не найдено", "Tail {} not found";
+    UnknownEncoding => "Неизвестная кодировка '{}'", "Unknown encoding '{}'";
+    InvalidBytesForEncoding => "Файл содержит байты, недопустимые в кодировке {}", "The file contains bytes that are invalid in the {} encoding";
+    UnmappableCharacter => "Символ '{}' нельзя записать в кодировке {}", "Character '{}' cannot be written in the {} encoding";
+
+    // Операции с файлами и папками
+    FileCreated => "Файл успешно создан", "File created successfully";
+    SudoFileCreateFailed => "Ошибка создания файла с sudo", "Failed to create the file with sudo";
+    FolderCreated => "Папка успешно создана", "Folder created successfully";
+    SudoFolderCreateFailed => "Ошибка создания папки с sudo", "Failed to create the folder with sudo";
+    FileMovedToTrash => "Файл перемещен в корзину", "File moved to the trash";
+    FolderMovedToTrash => "Папка перемещена в корзину", "Folder moved to the trash";
+    FileDeleted => "Файл успешно удален", "File deleted successfully";
+    SudoFileDeleteFailed => "Ошибка удаления файла с sudo", "Failed to delete the file with sudo";
+    FolderDeleted => "Папка успешно удалена", "Folder deleted successfully";
+    SudoFolderDeleteFailed => "Ошибка удаления папки с sudo", "Failed to delete the folder with sudo";
+    Renamed => "Переименование выполнено успешно", "Renamed successfully";
+    SudoRenameFailed => "Ошибка переименования с sudo", "Failed to rename with sudo";
+    Copied => "Копирование выполнено успешно", "Copied successfully";
+    SudoCopyFailed => "Ошибка копирования с sudo", "Failed to copy with sudo";
+    Moved => "Перемещение выполнено успешно", "Moved successfully";
+    SudoMoveFailed => "Ошибка перемещения с sudo", "Failed to move with sudo";
+    CannotReplaceFileWithFolder => "Нельзя заменить файл '{}' папкой", "Cannot replace file '{}' with a folder";
+    DestinationIsFolder => "Назначение '{}' — папка", "Destination '{}' is a folder";
+    SourceUnavailable => "Источник '{}' недоступен", "Source '{}' is not accessible";
+    PathSkipped => "'{}' уже существует и пропущен", "'{}' already exists and was skipped";
+    FileExists => "Файл '{}' уже существует", "File '{}' already exists";
+    ConflictCheckFailed => "Не удалось проверить совпадающие файлы в '{}'", "Could not check '{}' for conflicting files";
+    AttributesReadFailed => "Ошибка чтения атрибутов '{}'", "Failed to read the attributes of '{}'";
+    AttributesParseFailed => "Не удалось разобрать атрибуты '{}'", "Could not parse the attributes of '{}'";
+    InvalidMode => "Некорректные права доступа '{}'", "Invalid permissions '{}'";
+    InvalidUserName => "Некорректное имя пользователя '{}'", "Invalid user name '{}'";
+    InvalidGroupName => "Некорректное имя группы '{}'", "Invalid group name '{}'";
+    SudoChmodFailed => "Ошибка изменения прав с sudo", "Failed to change permissions with sudo";
+    SudoChownFailed => "Ошибка изменения владельца с sudo", "Failed to change the owner with sudo";
+    SudoChgrpFailed => "Ошибка изменения группы с sudo", "Failed to change the group with sudo";
+    DirectoryReadFailed => "Ошибка чтения директории {}", "Failed to read directory {}";
+    HomeDirResolveFailed => "Ошибка определения домашней папки", "Failed to determine the home folder";
+    OperationDelete => "удаление", "deletion";
+    OperationRename => "переименование", "renaming";
+    OperationWrite => "изменение", "modification";
+    InvalidPolicyPattern => "Шаблон защищенного пути '{}' должен быть абсолютным или начинаться с ~", "Protected path pattern '{}' must be absolute or start with ~";
+    PathForbidden => "'{}': {} запрещено правилом '{}'", "'{}': {} is forbidden by rule '{}'";
+    PathNeedsConfirmation => "'{}': {} требует подтверждения (правило '{}')", "'{}': {} requires confirmation (rule '{}')";
+
+    // Корзина
+    TrashNotConfigured => "Корзина для сервера не настроена", "The trash is not configured for the server";
+    TrashDirCreateFailed => "Ошибка создания папки корзины '{}'", "Failed to create the trash folder '{}'";
+    TrashEntryNotFound => "Запись корзины {} не найдена", "Trash entry {} not found";
+    TrashEntryReadFailed => "Ошибка чтения записи корзины {}", "Failed to read trash entry {}";
+    TrashEntryCorrupted => "Поврежденная запись корзины {}", "Trash entry {} is corrupted";
+    InvalidTrashId => "Некорректный ID записи корзины '{}'", "Invalid trash entry ID '{}'";
+    TrashEntrySerializeFailed => "Ошибка сериализации записи корзины", "Failed to serialize the trash entry";
+    TrashWriteFailed => "Ошибка записи в корзину", "Failed to write to the trash";
+    SudoTrashFailed => "Ошибка перемещения в корзину с sudo", "Failed to move to the trash with sudo";
+    RestoreTargetExists => "Нельзя восстановить: '{}' уже существует", "Cannot restore: '{}' already exists";
+    SudoRestoreFailed => "Ошибка восстановления из корзины с sudo", "Failed to restore from the trash with sudo";
+    Restored => "'{}' восстановлен", "'{}' restored";
+    SudoEmptyTrashFailed => "Ошибка очистки корзины с sudo", "Failed to empty the trash with sudo";
+    TrashEntriesDeleted => "Удалено из корзины: {}", "Deleted from the trash: {}";
+    TrashEmptied => "Корзина очищена", "Trash emptied";
+
+    // Передача между серверами
+    SourceReadFailed => "Ошибка чтения из исходного файла", "Failed to read from the source file";
+    DestinationWriteFailed => "Ошибка записи в файл назначения", "Failed to write to the destination file";
+    PermissionsParseFailed => "Ошибка парсинга прав доступа", "Failed to parse permissions";
+    DestinationSftpFailed => "Ошибка создания SFTP канала получателя", "Failed to open an SFTP channel to the destination";
+    SourceSftpFailed => "Ошибка создания SFTP канала источника", "Failed to open an SFTP channel to the source";
+    SourceOpenFailed => "Ошибка открытия исходного файла", "Failed to open the source file";
+    SourceStatFailed => "Ошибка чтения атрибутов исходного файла", "Failed to read the attributes of the source file";
+    SudoFileCopyFailed => "Ошибка копирования файла с sudo", "Failed to copy the file with sudo";
+    SudoDirectoryCreateFailed => "Ошибка создания директории с sudo", "Failed to create the directory with sudo";
+    FolderCopied => "Папка '{}' успешно скопирована", "Folder '{}' copied successfully";
+    FileCopied => "Файл '{}' успешно скопирован", "File '{}' copied successfully";
+    FileSkipped => "Файл '{}' уже существует и пропущен", "File '{}' already exists and was skipped";
+    TransferSummary => "{} (скопировано файлов: {}, пропущено существующих: {})", "{} (files copied: {}, existing skipped: {})";
+
+    // Передача с компьютера и на компьютер
+    LocalFileOpenFailed => "Ошибка открытия локального файла '{}'", "Failed to open local file '{}'";
+    LocalFileCreateFailed => "Ошибка создания локального файла '{}'", "Failed to create local file '{}'";
+    LocalFolderReadFailed => "Ошибка чтения папки '{}'", "Failed to read folder '{}'";
+    LocalFolderCreateFailed => "Ошибка создания папки '{}'", "Failed to create folder '{}'";
+    LocalNotFolder => "'{}' существует и не является папкой", "'{}' exists and is not a folder";
+    LocalPathUnavailable => "Локальный путь '{}' недоступен", "Local path '{}' is not accessible";
+    RemoteFileOpenFailed => "Ошибка открытия файла '{}' на сервере", "Failed to open file '{}' on the server";
+    RemoteFileCreateFailed => "Ошибка создания файла '{}' на сервере", "Failed to create file '{}' on the server";
+    RemoteFileReplaceFailed => "Ошибка замены файла '{}' на сервере", "Failed to replace file '{}' on the server";
+    RemoteFolderCreateFailed => "Ошибка создания папки '{}' на сервере", "Failed to create folder '{}' on the server";
+    RemoteNotFolder => "'{}' на сервере существует и не является папкой", "'{}' exists on the server and is not a folder";
+    RemotePathUnavailable => "Путь '{}' на сервере недоступен", "Path '{}' on the server is not accessible";
+    RenameFailed => "Ошибка переименования '{}' в '{}'", "Failed to rename '{}' to '{}'";
+    AttributesSetFailed => "Ошибка установки атрибутов '{}'", "Failed to set the attributes of '{}'";
+    MtimeSetFailed => "Ошибка установки времени изменения '{}'", "Failed to set the modification time of '{}'";
+    PermissionsSetFailed => "Ошибка установки прав '{}'", "Failed to set the permissions of '{}'";
+    FolderUploaded => "Папка '{}' успешно загружена", "Folder '{}' uploaded successfully";
+    FileUploaded => "Файл '{}' успешно загружен", "File '{}' uploaded successfully";
+    FolderDownloaded => "Папка '{}' успешно скачана", "Folder '{}' downloaded successfully";
+    FileDownloaded => "Файл '{}' успешно скачан", "File '{}' downloaded successfully";
+
+    // Фоновые передачи и докачка
+    TransferCrashed => "Передача прервана из-за внутренней ошибки", "The transfer stopped because of an internal error";
+    TransferCancelled => "Передача отменена", "Transfer cancelled";
+    JobNotFound => "Задача {} не найдена", "Job {} not found";
+    TransferDequeued => "Передача удалена из очереди", "Transfer removed from the queue";
+    TransferStopping => "Передача будет остановлена", "The transfer will be stopped";
+    JobFinished => "Задача {} уже завершена", "Job {} has already finished";
+    JournalSerializeFailed => "Ошибка сериализации журнала передач", "Failed to serialize the transfer journal";
+    JournalWriteFailed => "Ошибка записи журнала передач", "Failed to write the transfer journal";
+    PartialFilePrepareFailed => "Ошибка подготовки частичного файла", "Failed to prepare the partial file";
+
+    // Аутентификация и ключи хостов
+    AuthFailed => "Ошибка аутентификации", "Authentication failed";
+    AuthRejected => "Ошибка аутентификации: сервер отклонил учетные данные", "Authentication failed: the server rejected the credentials";
+    KeyFileNotFound => "Файл ключа {} не найден", "Key file {} not found";
+    KeyAuthFailed => "Ошибка аутентификации по ключу {}", "Authentication with key {} failed";
+    AgentInitFailed => "Ошибка инициализации ssh-agent", "Failed to initialize ssh-agent";
+    AgentConnectFailed => "Не удалось подключиться к ssh-agent", "Could not connect to ssh-agent";
+    AgentKeysFailed => "Ошибка получения ключей из ssh-agent", "Failed to get keys from ssh-agent";
+    AgentNoKeys => "В ssh-agent нет загруженных ключей", "ssh-agent has no keys loaded";
+    AgentKeysRejected => "Ошибка аутентификации: ни один ключ из ssh-agent не подошел", "Authentication failed: none of the ssh-agent keys were accepted";
+    HostKeyFingerprintFailed => "Не удалось получить отпечаток ключа хоста", "Could not get the host key fingerprint";
+    KnownHostsInitFailed => "Ошибка инициализации known_hosts", "Failed to initialize known_hosts";
+    KnownHostsFileReadFailed => "Ошибка чтения {}", "Failed to read {}";
+    KnownHostsFileWriteFailed => "Ошибка записи {}", "Failed to write {}";
+    KnownHostsReadFailed => "Ошибка чтения known_hosts", "Failed to read known_hosts";
+    NoHostKey => "Сервер не предоставил ключ хоста", "The server did not provide a host key";
+    HostUnknown => "Подлинность хоста {} не установлена", "The authenticity of host {} cannot be established";
+    HostKeyChanged => "Ключ хоста {} изменился! Возможна атака посредника (MITM)", "The host key for {} has changed! A man-in-the-middle (MITM) attack is possible";
+    HostKeyCheckFailed => "Ошибка проверки ключа хоста", "Failed to check the host key";
+    FingerprintMismatch => "Отпечаток ключа хоста изменился: ожидался {}, получен {}", "The host key fingerprint has changed: expected {}, got {}";
+    HostKeyAlreadyKnown => "Ключ хоста уже известен", "The host key is already known";
+    HostKeyConflict => "Для хоста сохранен другой ключ. Удалите старый ключ, прежде чем доверять новому", "A different key is saved for this host. Remove the old key before trusting the new one";
+    HostKeyAddFailed => "Ошибка добавления ключа хоста", "Failed to add the host key";
+    HostKeySaved => "Ключ хоста {} сохранен", "Host key {} saved";
+    NoKnownHosts => "Сохраненных ключей хостов нет", "There are no saved host keys";
+    HostKeyRemoveFailed => "Ошибка удаления ключа хоста", "Failed to remove the host key";
+    HostKeyNotFound => "Ключ хоста {} не найден", "Host key {} not found";
+    HostKeyRemoved => "Ключ хоста {} удален", "Host key {} removed";
+
+    // Адрес сервера
+    MissingUserName => "Не указано имя пользователя. Используйте 'user@host'", "No user name given. Use 'user@host'";
+    MissingHost => "Не указан хост. Используйте 'user@host' или 'user@host:port'", "No host given. Use 'user@host' or 'user@host:port'";
+    UnclosedBracket => "Не закрыта квадратная скобка в адресе '{}'", "Unclosed square bracket in address '{}'";
+    InvalidAddress => "Неверный формат адреса '{}'", "Invalid address format '{}'";
+    InvalidPort => "Неверный номер порта '{}'", "Invalid port number '{}'";
+
+    // Каналы, sudo, сессии и терминал
+    ChannelOpenFailed => "Ошибка создания канала", "Failed to open a channel";
+    CommandExecFailed => "Ошибка выполнения команды", "Failed to run the command";
+    CommandOutputReadFailed => "Ошибка чтения вывода команды", "Failed to read the command output";
+    ChannelCloseFailed => "Ошибка закрытия канала", "Failed to close the channel";
+    SudoWrongPassword => "Неверный пароль sudo", "Incorrect sudo password";
+    SudoPasswordMissing => "sudo запрашивает пароль, но пароль sudo для сервера не задан", "sudo asks for a password, but no sudo password is set for the server";
+    SudoPasswordSendFailed => "Ошибка передачи пароля sudo", "Failed to send the sudo password";
+    SudoOutputReadFailed => "Ошибка чтения вывода sudo", "Failed to read the sudo output";
+    SudoPasswordRequired => "sudo требует пароль, а для сервера выбран режим без пароля (NOPASSWD)", "sudo requires a password, but the server is set to passwordless mode (NOPASSWD)";
+    SudoNotAllowed => "Пользователю запрещено использовать sudo: {}", "The user is not allowed to use sudo: {}";
+    SudoNotInstalled => "На сервере не установлен sudo", "sudo is not installed on the server";
+    SessionReplaced => "Сессия заменена", "Session replaced";
+    SessionClosed => "Сессия закрыта", "Session closed";
+    CredentialsChanged => "Учетные данные изменились", "Credentials changed";
+    SessionIdleTimeout => "Сессия закрыта по таймауту простоя", "Session closed after the idle timeout";
+    ServerSessionClosed => "Сессия сервера {} закрыта", "Session for server {} closed";
+    NoServerSession => "Для сервера {} нет открытой сессии", "There is no open session for server {}";
+    TerminalIdInUse => "Терминал {} уже открыт", "Terminal {} is already open";
+    TerminalNotFound => "Терминал {} не найден", "Terminal {} not found";
+    TerminalAlreadyClosed => "Терминал {} уже закрыт", "Terminal {} is already closed";
+    PtyRequestFailed => "Ошибка запроса PTY", "Failed to request a PTY";
+    ShellStartFailed => "Ошибка запуска shell", "Failed to start the shell";
+    TerminalClosed => "Терминал закрыт", "Terminal closed";
+
+    // Хранилище учетных данных
+    VaultSaltCorrupted => "Поврежден файл хранилища (соль)", "The vault file is corrupted (salt)";
+    InvalidKdfParams => "Неверные параметры KDF", "Invalid KDF parameters";
+    KeyDerivationFailed => "Ошибка вычисления ключа", "Failed to derive the key";
+    SecretEncryptFailed => "Ошибка шифрования секрета", "Failed to encrypt the secret";
+    SecretCorrupted => "Поврежден зашифрованный секрет", "The encrypted secret is corrupted";
+    SecretDecryptFailed => "Не удалось расшифровать секрет: неверный мастер-пароль или поврежденные данные", "Could not decrypt the secret: wrong master password or corrupted data";
+    SecretNotUtf8 => "Расшифрованный секрет не является строкой UTF-8", "The decrypted secret is not a UTF-8 string";
+    VaultIsLocked => "Хранилище учетных данных заблокировано. Введите мастер-пароль", "The credential vault is locked. Enter the master password";
+    VaultReadFailed => "Ошибка чтения файла хранилища", "Failed to read the vault file";
+    VaultParseFailed => "Ошибка парсинга файла хранилища", "Failed to parse the vault file";
+    VaultSerializeFailed => "Ошибка сериализации файла хранилища", "Failed to serialize the vault file";
+    VaultWriteFailed => "Ошибка записи файла хранилища", "Failed to write the vault file";
+    WrongMasterPassword => "Неверный мастер-пароль", "Wrong master password";
+    EmptyMasterPassword => "Мастер-пароль не может быть пустым", "The master password cannot be empty";
+    VaultCreated => "Хранилище создано, зашифровано серверов: {}", "Vault created, servers encrypted: {}";
+    VaultUnlocked => "Хранилище разблокировано", "Vault unlocked";
+    VaultLocked => "Хранилище заблокировано", "Vault locked";
+    VaultFileMissing => "В servers.json есть зашифрованные пароли, но vault.json не найден. Верните vault.json, чтобы их расшифровать", "servers.json contains encrypted passwords, but vault.json is missing. Restore vault.json to decrypt them";
+    VaultNotCreated => "Хранилище еще не создано", "The vault has not been created yet";
+    MasterPasswordChanged => "Мастер-пароль изменен", "Master password changed";
+}
+
+impl Msg {
+    /// Текст на текущем языке.
+    pub fn text(self) -> &'static str {
+        self.text_in(language())
+    }
+
+    /// Текст с аргументами, подставленными по порядку вместо `{}`.
+    pub fn format(self, args: &[&dyn fmt::Display]) -> String {
+        fill(self.text(), args)
+    }
+
+    /// Сообщение об успехе, к которому добавляется «с правами администратора», если понадобился sudo.
+    pub fn elevated(self, elevated: bool) -> String {
+        if elevated {
+            Msg::AsAdministrator.format(&[&self.text()])
+        } else {
+            self.text().to_string()
+        }
+    }
+}
+
+fn fill(template: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+
+    if let Some(first) = parts.next() {
+        result.push_str(first);
+    }
+
+    for part in parts {
+        if let Some(arg) = args.next() {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+
+    result
+}
+
+/// Язык, на котором сейчас приходят сообщения, с учетом локали системы.
+#[command]
+pub fn get_language() -> Result<Language, AppError> {
+    Ok(language())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_language_from_locale() {
+        assert_eq!(Language::from_locale("ru_RU.UTF-8"), Some(Language::Ru));
+        assert_eq!(Language::from_locale("en-US"), Some(Language::En));
+        assert_eq!(Language::from_locale("de_DE@euro"), Some(Language::En));
+        assert_eq!(Language::from_locale("C.UTF-8"), None);
+        assert_eq!(Language::from_locale(""), None);
+    }
+
+    #[test]
+    fn translations_take_the_same_arguments() {
+        for message in Msg::ALL {
+            let ru = message.text_in(Language::Ru).matches("{}").count();
+            let en = message.text_in(Language::En).matches("{}").count();
+            assert_eq!(ru, en, "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn fills_arguments_in_order() {
+        assert_eq!(fill("{} (код {}): {}", &[&"Ошибка", &1, &"boom"]), "Ошибка (код 1): boom");
+        assert_eq!(fill("Готово", &[]), "Готово");
+    }
+}
@@ -0,0 +1,235 @@
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::json;
+use ssh2::{CheckResult, HashType, HostKeyType, KnownHostFileKind, KnownHosts, Session};
+use std::path::PathBuf;
+use tauri::command;
+
+use crate::connection::handshake;
+use crate::connection_target::{ConnectionTarget, DEFAULT_SSH_PORT};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::storage::{get_config_dir, load_app_settings};
+
+#[derive(Debug, Serialize)]
+pub struct HostKeyInfo {
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    pub fingerprint: String,
+}
+
+fn get_known_hosts_path() -> Result<PathBuf, AppError> {
+    let mut path = get_config_dir()?;
+    path.push("known_hosts");
+    Ok(path)
+}
+
+fn get_system_known_hosts_path() -> Option<PathBuf> {
+    let home_dir = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .ok()?;
+
+    let mut path = PathBuf::from(home_dir);
+    path.push(".ssh");
+    path.push("known_hosts");
+    Some(path)
+}
+
+/// Имя хоста в формате known_hosts: для нестандартного порта — `[host]:port`.
+fn known_hosts_entry(host: &str, port: u16) -> String {
+    if port == DEFAULT_SSH_PORT {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn key_type_name(key_type: HostKeyType) -> &'static str {
+    match key_type {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed25519 => "ssh-ed25519",
+        HostKeyType::Unknown => "unknown",
+    }
+}
+
+fn host_key_fingerprint(sess: &Session) -> Result<String, AppError> {
+    let hash = sess.host_key_hash(HashType::Sha256)
+        .ok_or_else(|| AppError::internal(Msg::HostKeyFingerprintFailed.text()))?;
+
+    Ok(format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)))
+}
+
+fn load_known_hosts(sess: &Session) -> Result<KnownHosts, AppError> {
+    let mut known_hosts = sess.known_hosts()
+        .map_err(|e| AppError::ssh(Msg::KnownHostsInitFailed.text(), &e))?;
+
+    let app_path = get_known_hosts_path()?;
+    if app_path.exists() {
+        known_hosts.read_file(&app_path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| AppError::ssh(&Msg::KnownHostsFileReadFailed.format(&[&app_path.display()]), &e))?;
+    }
+
+    if load_app_settings()?.use_system_known_hosts {
+        if let Some(system_path) = get_system_known_hosts_path() {
+            // Системный файл может содержать записи, которые libssh2 не понимает, поэтому ошибки игнорируются
+            if system_path.exists() {
+                let _ = known_hosts.read_file(&system_path, KnownHostFileKind::OpenSSH);
+            }
+        }
+    }
+
+    Ok(known_hosts)
+}
+
+/// Проверяет ключ хоста после рукопожатия. Неизвестный или изменившийся ключ возвращается
+/// с кодом `host_key_unknown` или `host_key_mismatch`, а в `details` — все, что нужно
+/// для запроса доверия (TOFU) или предупреждения о подмене ключа.
+pub fn verify_host_key(sess: &Session, host: &str, port: u16) -> Result<(), AppError> {
+    let (key, key_type) = sess.host_key()
+        .ok_or_else(|| AppError::new(ErrorCode::HostUnreachable, Msg::NoHostKey.text()))?;
+
+    let known_hosts = load_known_hosts(sess)?;
+
+    let (code, message) = match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => return Ok(()),
+        CheckResult::NotFound => (
+            ErrorCode::HostKeyUnknown,
+            Msg::HostUnknown.format(&[&known_hosts_entry(host, port)]),
+        ),
+        CheckResult::Mismatch => (
+            ErrorCode::HostKeyMismatch,
+            Msg::HostKeyChanged.format(&[&known_hosts_entry(host, port)]),
+        ),
+        CheckResult::Failure => return Err(AppError::internal(Msg::HostKeyCheckFailed.text())),
+    };
+
+    Err(AppError::new(code, message).with_details(json!({
+        "host": host,
+        "port": port,
+        "key_type": key_type_name(key_type),
+        "fingerprint": host_key_fingerprint(sess)?,
+    })))
+}
+
+fn parse_target(host: &str, port: Option<u16>) -> Result<ConnectionTarget, AppError> {
+    // Имя пользователя для проверки ключа не нужно
+    ConnectionTarget::parse(host, "-", port.unwrap_or(DEFAULT_SSH_PORT))
+}
+
+/// Получает ключ хоста без аутентификации, чтобы показать пользователю отпечаток.
+#[command]
+pub fn get_host_key_info(host: String, port: Option<u16>) -> Result<HostKeyInfo, AppError> {
+    let target = parse_target(&host, port)?;
+    let sess = handshake(&target)?;
+
+    let (_, key_type) = sess.host_key()
+        .ok_or_else(|| AppError::new(ErrorCode::HostUnreachable, Msg::NoHostKey.text()))?;
+
+    Ok(HostKeyInfo {
+        host: target.host.clone(),
+        port: target.port,
+        key_type: key_type_name(key_type).to_string(),
+        fingerprint: host_key_fingerprint(&sess)?,
+    })
+}
+
+/// Сохраняет ключ хоста после подтверждения пользователем (trust-on-first-use).
+/// Отпечаток сверяется повторно, чтобы не доверить ключу, подмененному между запросами.
+#[command]
+pub fn trust_host_key(host: String, port: Option<u16>, fingerprint: String) -> Result<String, AppError> {
+    let target = parse_target(&host, port)?;
+    let sess = handshake(&target)?;
+
+    let current_fingerprint = host_key_fingerprint(&sess)?;
+    if current_fingerprint != fingerprint {
+        return Err(AppError::new(
+            ErrorCode::HostKeyMismatch,
+            Msg::FingerprintMismatch.format(&[&fingerprint, &current_fingerprint]),
+        )
+        .with_details(json!({ "expected": fingerprint, "fingerprint": current_fingerprint })));
+    }
+
+    let (key, key_type) = sess.host_key()
+        .ok_or_else(|| AppError::new(ErrorCode::HostUnreachable, Msg::NoHostKey.text()))?;
+
+    let known_hosts = load_known_hosts(&sess)?;
+
+    match known_hosts.check_port(&target.host, target.port, key) {
+        CheckResult::Match => return Ok(Msg::HostKeyAlreadyKnown.text().to_string()),
+        CheckResult::Mismatch => {
+            return Err(AppError::new(
+                ErrorCode::HostKeyMismatch,
+                Msg::HostKeyConflict.text(),
+            ))
+        }
+        CheckResult::Failure => return Err(AppError::internal(Msg::HostKeyCheckFailed.text())),
+        CheckResult::NotFound => {}
+    }
+
+    // Записываем только в файл приложения, системный ~/.ssh/known_hosts не меняем
+    let app_path = get_known_hosts_path()?;
+    let mut app_known_hosts = sess.known_hosts()
+        .map_err(|e| AppError::ssh(Msg::KnownHostsInitFailed.text(), &e))?;
+
+    if app_path.exists() {
+        app_known_hosts.read_file(&app_path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| AppError::ssh(&Msg::KnownHostsFileReadFailed.format(&[&app_path.display()]), &e))?;
+    }
+
+    let entry = known_hosts_entry(&target.host, target.port);
+
+    app_known_hosts.add(&entry, key, "added by ssh-connect", key_type.into())
+        .map_err(|e| AppError::ssh(Msg::HostKeyAddFailed.text(), &e))?;
+
+    app_known_hosts.write_file(&app_path, KnownHostFileKind::OpenSSH)
+        .map_err(|e| AppError::ssh(&Msg::KnownHostsFileWriteFailed.format(&[&app_path.display()]), &e))?;
+
+    Ok(Msg::HostKeySaved.format(&[&entry]))
+}
+
+/// Удаляет сохраненный ключ хоста из known_hosts приложения (например, после переустановки сервера).
+#[command]
+pub fn forget_host_key(host: String, port: Option<u16>) -> Result<String, AppError> {
+    let target = parse_target(&host, port)?;
+    let app_path = get_known_hosts_path()?;
+
+    if !app_path.exists() {
+        return Err(AppError::not_found(Msg::NoKnownHosts.text()));
+    }
+
+    // Для работы с known_hosts libssh2 нужна сессия, но не соединение
+    let sess = Session::new()
+        .map_err(|e| AppError::ssh(Msg::SessionCreateFailed.text(), &e))?;
+
+    let mut known_hosts = sess.known_hosts()
+        .map_err(|e| AppError::ssh(Msg::KnownHostsInitFailed.text(), &e))?;
+
+    known_hosts.read_file(&app_path, KnownHostFileKind::OpenSSH)
+        .map_err(|e| AppError::ssh(&Msg::KnownHostsFileReadFailed.format(&[&app_path.display()]), &e))?;
+
+    let entry = known_hosts_entry(&target.host, target.port);
+    let hosts = known_hosts.hosts()
+        .map_err(|e| AppError::ssh(Msg::KnownHostsReadFailed.text(), &e))?;
+
+    let mut removed = 0;
+    for known_host in hosts.iter().filter(|h| h.name() == Some(entry.as_str())) {
+        known_hosts.remove(known_host)
+            .map_err(|e| AppError::ssh(Msg::HostKeyRemoveFailed.text(), &e))?;
+        removed += 1;
+    }
+
+    if removed == 0 {
+        return Err(AppError::not_found(Msg::HostKeyNotFound.format(&[&entry])));
+    }
+
+    known_hosts.write_file(&app_path, KnownHostFileKind::OpenSSH)
+        .map_err(|e| AppError::ssh(&Msg::KnownHostsFileWriteFailed.format(&[&app_path.display()]), &e))?;
+
+    Ok(Msg::HostKeyRemoved.format(&[&entry]))
+}
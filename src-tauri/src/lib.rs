@@ -2,8 +2,27 @@ mod ssh;
 mod listdirectory;
 mod storage;
 mod file;
+mod file_stream;
 mod file_operations;
 mod connect_copy;
+mod session_pool;
+mod auth;
+mod connection;
+mod connection_target;
+mod known_hosts;
+mod vault;
+mod terminal;
+mod remote_command;
+mod local_transfer;
+mod transfer_jobs;
+mod transfer_resume;
+mod privilege;
+mod text_encoding;
+mod content_type;
+mod trash;
+mod path_policy;
+mod error;
+mod i18n;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -12,26 +31,69 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let session_pool = session_pool::SessionPool::default();
+    session_pool.start_reaper();
+
+    i18n::set_language(storage::load_app_settings().ok().and_then(|settings| settings.language));
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .manage(session_pool)
+        .manage(vault::Vault::default())
+        .manage(terminal::TerminalManager::default())
+        .manage(transfer_jobs::TransferManager::default())
+        .manage(file_stream::TailManager::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             ssh::ssh_connect,
+            session_pool::disconnect_server,
+            terminal::open_terminal,
+            terminal::write_terminal,
+            terminal::resize_terminal,
+            terminal::close_terminal,
             listdirectory::list_directory,
             storage::add_server_to_config,
             storage::update_server_in_config,
             storage::remove_server_from_config,
             storage::load_servers_from_config,
             storage::get_config_path,
+            storage::load_settings,
+            storage::save_settings,
+            i18n::get_language,
+            known_hosts::get_host_key_info,
+            known_hosts::trust_host_key,
+            known_hosts::forget_host_key,
+            vault::get_vault_status,
+            vault::unlock_vault,
+            vault::lock_vault,
+            vault::rekey_vault,
             file::check_file_permissions,
             file::save_file_content,
             file::read_file_content,
+            file_stream::read_file_range,
+            file_stream::read_file_hex,
+            file_stream::start_file_tail,
+            file_stream::stop_file_tail,
             file_operations::create_file,
             file_operations::create_directory,
             file_operations::delete_file,
             file_operations::delete_directory,
             file_operations::rename_file,
+            file_operations::copy_path,
+            file_operations::move_path,
+            file_operations::change_mode,
+            file_operations::change_owner,
+            file_operations::change_group,
+            trash::list_trash,
+            trash::restore_from_trash,
+            trash::empty_trash,
             connect_copy::transfer_file_between_servers,
+            local_transfer::upload_to_server,
+            local_transfer::download_from_server,
+            transfer_jobs::start_transfer,
+            transfer_jobs::cancel_transfer,
+            transfer_jobs::list_transfers,
+            transfer_jobs::clear_finished_transfers,
             
         ])
         .run(tauri::generate_context!())
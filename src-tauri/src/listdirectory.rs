@@ -1,123 +1,145 @@
 use serde::{Deserialize, Serialize};
-use ssh2::Session;
-use std::io::Read;
-use std::net::TcpStream;
-use tauri::command;
+use ssh2::{Session, Sftp};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use tauri::{command, State};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SshConnectionInfo {
-    pub username: String,
-    pub host: String,
-    pub password: String,
-}
+use crate::connection::{get_session, SshConnectionInfo};
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::remote_command::{run_command, RemoteCommand};
+use crate::session_pool::SessionPool;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
+    /// Для символической ссылки — указывает ли она на директорию
     pub is_folder: bool,
     pub path: String,
+    pub size: Option<u64>,
+    /// Время последнего изменения, секунды Unix
+    pub modified: Option<u64>,
+    /// Биты прав доступа (без типа файла), например 0o755
+    pub permissions: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub is_symlink: bool,
+    pub link_target: Option<String>,
 }
 
 
 #[command]
-pub fn list_directory(connection_info: SshConnectionInfo, path: String) -> Result<Vec<FileEntry>, String> {
-    // Парсим строку вида "user@host" на компоненты
-    let host_string = if connection_info.host.contains('@') {
-        connection_info.host.clone()
-    } else {
-        format!("{}@{}", connection_info.username, connection_info.host)
-    };
+pub fn list_directory(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, path: String) -> Result<Vec<FileEntry>, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
 
-    let parts: Vec<&str> = host_string.split('@').collect();
-    
-    if parts.len() != 2 {
-        return Err("Неверный формат строки подключения. Используйте 'user@host'".to_string());
-    }
-    
-    let username = parts[0];
-    let host = parts[1];
-
-    // Подключение к SSH-серверу
-    let tcp = match TcpStream::connect(format!("{}:22", host)) {
-        Ok(stream) => stream,
-        Err(e) => return Err(format!("Ошибка подключения к серверу: {}", e)),
-    };
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
 
-    let mut sess = match Session::new() {
-        Ok(session) => session,
-        Err(e) => return Err(format!("Ошибка создания SSH-сессии: {}", e)),
-    };
+    let mut entries = read_directory(&sftp, &path)?;
+    resolve_owner_names(&sess, &mut entries);
 
-    sess.set_tcp_stream(tcp);
-    
-    if let Err(e) = sess.handshake() {
-        return Err(format!("Ошибка при рукопожатии SSH: {}", e));
-    }
+    Ok(entries)
+}
 
-    // Аутентификация с помощью пароля
-    if let Err(e) = sess.userauth_password(username, &connection_info.password) {
-        return Err(format!("Ошибка аутентификации: {}", e));
-    }
+/// Читает содержимое директории через SFTP. Для символических ссылок дополнительно
+/// читается цель ссылки и тип объекта, на который она указывает.
+pub fn read_directory(sftp: &Sftp, path: &str) -> Result<Vec<FileEntry>, AppError> {
+    let items = sftp.readdir(Path::new(path))
+        .map_err(|e| AppError::ssh(&Msg::DirectoryReadFailed.format(&[&path]), &e))?;
 
-    // Открываем канал для выполнения команды
-    let mut channel = match sess.channel_session() {
-        Ok(channel) => channel,
-        Err(e) => return Err(format!("Ошибка создания канала: {}", e)),
-    };
+    let mut entries = Vec::with_capacity(items.len());
 
-    // Выполняем команду ls с форматированием для определения типа файла
-    let command = format!("ls -la {}", path);
-    
-    if let Err(e) = channel.exec(&command) {
-        return Err(format!("Ошибка выполнения команды: {}", e));
-    }
+    for (item_path, stat) in items {
+        let name = match item_path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
 
-    let mut output = String::new();
-    if let Err(e) = channel.read_to_string(&mut output) {
-        return Err(format!("Ошибка чтения вывода команды: {}", e));
-    }
+        if name == "." || name == ".." {
+            continue;
+        }
 
-    if let Err(e) = channel.wait_close() {
-        return Err(format!("Ошибка закрытия канала: {}", e));
+        let is_symlink = stat.file_type().is_symlink();
+
+        let (is_folder, link_target) = if is_symlink {
+            let target_is_dir = sftp.stat(&item_path).map(|target| target.is_dir()).unwrap_or(false);
+            let link_target = sftp.readlink(&item_path)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string());
+            (target_is_dir, link_target)
+        } else {
+            (stat.is_dir(), None)
+        };
+
+        let full_path = if path.ends_with('/') {
+            format!("{}{}", path, name)
+        } else {
+            format!("{}/{}", path, name)
+        };
+
+        entries.push(FileEntry {
+            name,
+            is_folder,
+            path: full_path,
+            size: stat.size,
+            modified: stat.mtime,
+            permissions: stat.perm.map(|perm| perm & 0o7777),
+            uid: stat.uid,
+            gid: stat.gid,
+            owner: None,
+            group: None,
+            is_symlink,
+            link_target,
+        });
     }
 
-    let exit_status = channel.exit_status().unwrap_or(-1);
-    
-    if exit_status != 0 {
-        return Err(format!("Команда завершилась с ошибкой: {}", exit_status));
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(entries)
+}
+
+/// SFTP отдает только числовые uid/gid, поэтому имена получаем одним вызовом getent.
+/// Если getent недоступен, имена остаются пустыми.
+fn resolve_owner_names(sess: &Session, entries: &mut [FileEntry]) {
+    let uids: BTreeSet<u32> = entries.iter().filter_map(|e| e.uid).collect();
+    let gids: BTreeSet<u32> = entries.iter().filter_map(|e| e.gid).collect();
+
+    if uids.is_empty() && gids.is_empty() {
+        return;
     }
 
-    // Парсинг вывода команды ls -la
-    let mut entries = Vec::new();
-    
-    for line in output.lines().skip(1) { // Пропускаем первую строку (total)
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        
-        if parts.len() >= 9 {
-            let permissions = parts[0];
-            let filename = parts[8..].join(" ");
-            
-            // Пропускаем . и ..
-            if filename == "." || filename == ".." {
-                continue;
-            }
-            
-            // Проверяем первый символ прав доступа для определения типа (d - директория)
-            let is_folder = permissions.starts_with('d');
-            
-            let full_path = if path.ends_with('/') {
-                format!("{}{}", path, filename)
-            } else {
-                format!("{}/{}", path, filename)
-            };
-            
-            entries.push(FileEntry {
-                name: filename,
-                is_folder,
-                path: full_path,
-            });
-        }
+    let getent = |database: &'static str, ids: &BTreeSet<u32>| {
+        ids.iter().fold(RemoteCommand::new("getent").arg(database), |command, id| command.arg(id.to_string()))
+    };
+    let command = getent("passwd", &uids)
+        .then(RemoteCommand::new("echo").arg("--"))
+        .then(getent("group", &gids));
+
+    let output = match run_command(sess, &command) {
+        Ok(output) => output.stdout,
+        Err(_) => return,
+    };
+
+    let (passwd, group) = output.split_once("--\n").unwrap_or((output.as_str(), ""));
+    let users = parse_getent(passwd);
+    let groups = parse_getent(group);
+
+    for entry in entries.iter_mut() {
+        entry.owner = entry.uid.and_then(|uid| users.get(&uid).cloned());
+        entry.group = entry.gid.and_then(|gid| groups.get(&gid).cloned());
     }
+}
 
-    Ok(entries)
-}
\ No newline at end of file
+fn parse_getent(output: &str) -> HashMap<u32, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse::<u32>().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,382 @@
+use serde::Deserialize;
+use ssh2::{FileStat, OpenFlags, OpenType, RenameFlags, Sftp};
+use std::fs::{self, File, FileTimes, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{command, State};
+
+use crate::connect_copy::{OverwritePolicy, TransferSummary, WalkEntry};
+use crate::connection::{get_session, SshConnectionInfo};
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::listdirectory::read_directory;
+use crate::path_policy::{check_paths, PolicyOperation};
+use crate::session_pool::SessionPool;
+use crate::transfer_resume::{copy_resumable, forget, ResumableFile, SourceVersion};
+
+const DEFAULT_FILE_MODE: u32 = 0o644;
+const DEFAULT_DIR_MODE: u32 = 0o755;
+const PARTIAL_SUFFIX: &str = ".part";
+const REPLACED_SUFFIX: &str = ".ssh-connect-old";
+
+/// Передача между компьютером пользователя и сервером. Пути — полные пути назначения и источника;
+/// файл это или папка, определяется по источнику.
+#[derive(Debug, Deserialize)]
+pub struct LocalTransferRequest {
+    pub connection: SshConnectionInfo,
+    pub local_path: String,
+    pub remote_path: String,
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    /// Пользователь подтвердил запись в путь, защищенный политикой с подтверждением
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+#[cfg(unix)]
+fn local_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn local_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_local_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn set_local_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+fn unix_seconds(time: io::Result<SystemTime>) -> Option<u64> {
+    time.ok()?.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn file_times(atime: Option<u64>, mtime: u64) -> FileTimes {
+    FileTimes::new()
+        .set_accessed(UNIX_EPOCH + Duration::from_secs(atime.unwrap_or(mtime)))
+        .set_modified(UNIX_EPOCH + Duration::from_secs(mtime))
+}
+
+/// Права и время изменения локального файла для записи на сервер через setstat.
+fn remote_attributes(metadata: &fs::Metadata, default_mode: u32) -> FileStat {
+    let mtime = unix_seconds(metadata.modified());
+
+    FileStat {
+        size: None,
+        uid: None,
+        gid: None,
+        perm: Some(local_mode(metadata).unwrap_or(default_mode)),
+        atime: unix_seconds(metadata.accessed()).or(mtime),
+        mtime,
+    }
+}
+
+/// Недокачанный файл лежит рядом с файлом назначения и переименовывается после завершения.
+pub fn partial_name(path: &str) -> String {
+    format!("{}{}", path, PARTIAL_SUFFIX)
+}
+
+fn remote_join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// Переименовывает недокачанный файл поверх файла назначения. SFTP v3 не заменяет файл
+/// при переименовании: тогда старый файл сначала отодвигается в сторону и возвращается
+/// на место, если переименование не удалось, а удаляется только после успеха.
+fn replace_remote_file(sftp: &Sftp, partial_path: &str, remote_path: &str) -> Result<(), AppError> {
+    let (partial, remote) = (Path::new(partial_path), Path::new(remote_path));
+    let flags = RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE;
+
+    if sftp.rename(partial, remote, Some(flags)).is_ok() {
+        return Ok(());
+    }
+
+    let rename_failed = |e: ssh2::Error| AppError::ssh(&Msg::RenameFailed.format(&[&partial_path, &remote_path]), &e);
+
+    if sftp.stat(remote).is_err() {
+        return sftp.rename(partial, remote, None).map_err(rename_failed);
+    }
+
+    let replaced_path = format!("{}{}", remote_path, REPLACED_SUFFIX);
+    let replaced = Path::new(&replaced_path);
+
+    // Копия могла остаться после прерванной загрузки
+    let _ = sftp.unlink(replaced);
+    sftp.rename(remote, replaced, None)
+        .map_err(|e| AppError::ssh(&Msg::RemoteFileReplaceFailed.format(&[&remote_path]), &e))?;
+
+    if let Err(e) = sftp.rename(partial, remote, None) {
+        let _ = sftp.rename(replaced, remote, None);
+        return Err(rename_failed(e));
+    }
+
+    let _ = sftp.unlink(replaced);
+    Ok(())
+}
+
+fn upload_file(sftp: &Sftp, endpoint: &str, local_path: &Path, remote_path: &str, policy: OverwritePolicy, summary: &mut TransferSummary) -> Result<(), AppError> {
+    summary.start_file(&local_path.display().to_string())?;
+
+    if !policy.should_copy(sftp.stat(Path::new(remote_path)).is_ok(), remote_path)? {
+        return summary.file_skipped();
+    }
+
+    let mut local_file = File::open(local_path)
+        .map_err(|e| AppError::io(&Msg::LocalFileOpenFailed.format(&[&local_path.display()]), &e))?;
+
+    let metadata = local_file.metadata()
+        .map_err(|e| AppError::io(&Msg::AttributesReadFailed.format(&[&local_path.display()]), &e))?;
+
+    let attributes = remote_attributes(&metadata, DEFAULT_FILE_MODE);
+
+    let resumable = ResumableFile::new(
+        local_path.display().to_string(),
+        format!("{}:{}", endpoint, remote_path),
+        SourceVersion {
+            size: Some(metadata.len()),
+            mtime: attributes.mtime,
+        },
+    );
+
+    let partial_path = partial_name(remote_path);
+    let partial_size = sftp.stat(Path::new(&partial_path)).ok().and_then(|stat| stat.size);
+
+    let mut remote_file = sftp
+        .open_mode(
+            Path::new(&partial_path),
+            OpenFlags::READ | OpenFlags::WRITE | OpenFlags::CREATE,
+            0o600,
+            OpenType::File,
+        )
+        .map_err(|e| AppError::ssh(&Msg::RemoteFileCreateFailed.format(&[&partial_path]), &e))?;
+
+    let copied = copy_resumable(&resumable, &mut local_file, &mut remote_file, partial_size, summary);
+
+    drop(remote_file);
+
+    if let Err(error) = copied {
+        if summary.is_cancelled() {
+            let _ = sftp.unlink(Path::new(&partial_path));
+        }
+        return Err(error);
+    }
+
+    replace_remote_file(sftp, &partial_path, remote_path)?;
+
+    // Права задаются явно, потому что при создании файла на сервере действует umask
+    sftp.setstat(Path::new(remote_path), attributes)
+        .map_err(|e| AppError::ssh(&Msg::AttributesSetFailed.format(&[&remote_path]), &e))?;
+
+    forget(&resumable.key);
+
+    summary.file_copied()
+}
+
+fn upload_directory(sftp: &Sftp, endpoint: &str, local_path: &Path, remote_path: &str, policy: OverwritePolicy, summary: &mut TransferSummary) -> Result<(), AppError> {
+    let metadata = fs::metadata(local_path)
+        .map_err(|e| AppError::io(&Msg::AttributesReadFailed.format(&[&local_path.display()]), &e))?;
+
+    let attributes = remote_attributes(&metadata, DEFAULT_DIR_MODE);
+
+    match sftp.stat(Path::new(remote_path)) {
+        Ok(stat) if stat.is_dir() => {}
+        Ok(_) => return Err(AppError::conflict(Msg::RemoteNotFolder.format(&[&remote_path]))),
+        Err(_) => sftp
+            .mkdir(Path::new(remote_path), attributes.perm.unwrap_or(DEFAULT_DIR_MODE) as i32)
+            .map_err(|e| AppError::ssh(&Msg::RemoteFolderCreateFailed.format(&[&remote_path]), &e))?,
+    }
+
+    let mut entries: Vec<_> = fs::read_dir(local_path)
+        .map_err(|e| AppError::io(&Msg::LocalFolderReadFailed.format(&[&local_path.display()]), &e))?
+        .filter_map(|entry| entry.ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let item_path = entry.path();
+        let remote_item_path = remote_join(remote_path, &name);
+
+        let is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+
+        match WalkEntry::of(is_symlink, item_path.is_dir()) {
+            WalkEntry::Skip => {}
+            WalkEntry::Folder => upload_directory(sftp, endpoint, &item_path, &remote_item_path, policy, summary)?,
+            WalkEntry::File => upload_file(sftp, endpoint, &item_path, &remote_item_path, policy, summary)?,
+        }
+    }
+
+    // Время папки выставляется после копирования содержимого, иначе запись файлов его изменит
+    let _ = sftp.setstat(Path::new(remote_path), attributes);
+
+    Ok(())
+}
+
+fn download_file(sftp: &Sftp, endpoint: &str, remote_path: &str, local_path: &Path, policy: OverwritePolicy, summary: &mut TransferSummary) -> Result<(), AppError> {
+    summary.start_file(remote_path)?;
+
+    if !policy.should_copy(local_path.exists(), &local_path.display().to_string())? {
+        return summary.file_skipped();
+    }
+
+    let mut remote_file = sftp.open(Path::new(remote_path))
+        .map_err(|e| AppError::ssh(&Msg::RemoteFileOpenFailed.format(&[&remote_path]), &e))?;
+
+    let stat = remote_file.stat()
+        .map_err(|e| AppError::ssh(&Msg::AttributesReadFailed.format(&[&remote_path]), &e))?;
+
+    let resumable = ResumableFile::new(
+        format!("{}:{}", endpoint, remote_path),
+        local_path.display().to_string(),
+        SourceVersion {
+            size: stat.size,
+            mtime: stat.mtime,
+        },
+    );
+
+    let partial_path = PathBuf::from(partial_name(&resumable.destination));
+    let partial_size = fs::metadata(&partial_path).ok().map(|metadata| metadata.len());
+
+    let mut local_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&partial_path)
+        .map_err(|e| AppError::io(&Msg::LocalFileCreateFailed.format(&[&partial_path.display()]), &e))?;
+
+    if let Err(error) = copy_resumable(&resumable, &mut remote_file, &mut local_file, partial_size, summary) {
+        drop(local_file);
+        if summary.is_cancelled() {
+            let _ = fs::remove_file(&partial_path);
+        }
+        return Err(error);
+    }
+
+    if let Some(mtime) = stat.mtime {
+        local_file.set_times(file_times(stat.atime, mtime))
+            .map_err(|e| AppError::io(&Msg::MtimeSetFailed.format(&[&partial_path.display()]), &e))?;
+    }
+
+    drop(local_file);
+
+    set_local_mode(&partial_path, stat.perm.unwrap_or(DEFAULT_FILE_MODE))
+        .map_err(|e| AppError::io(&Msg::PermissionsSetFailed.format(&[&partial_path.display()]), &e))?;
+
+    fs::rename(&partial_path, local_path)
+        .map_err(|e| AppError::io(&Msg::RenameFailed.format(&[&partial_path.display(), &local_path.display()]), &e))?;
+
+    forget(&resumable.key);
+
+    summary.file_copied()
+}
+
+fn download_directory(sftp: &Sftp, endpoint: &str, remote_path: &str, local_path: &Path, policy: OverwritePolicy, summary: &mut TransferSummary) -> Result<(), AppError> {
+    let stat = sftp.stat(Path::new(remote_path))
+        .map_err(|e| AppError::ssh(&Msg::AttributesReadFailed.format(&[&remote_path]), &e))?;
+
+    if local_path.exists() && !local_path.is_dir() {
+        return Err(AppError::conflict(Msg::LocalNotFolder.format(&[&local_path.display()])));
+    }
+
+    fs::create_dir_all(local_path)
+        .map_err(|e| AppError::io(&Msg::LocalFolderCreateFailed.format(&[&local_path.display()]), &e))?;
+
+    for entry in read_directory(sftp, remote_path)? {
+        let local_item_path = local_path.join(&entry.name);
+
+        match WalkEntry::of(entry.is_symlink, entry.is_folder) {
+            WalkEntry::Skip => {}
+            WalkEntry::Folder => download_directory(sftp, endpoint, &entry.path, &local_item_path, policy, summary)?,
+            WalkEntry::File => download_file(sftp, endpoint, &entry.path, &local_item_path, policy, summary)?,
+        }
+    }
+
+    let _ = set_local_mode(local_path, stat.perm.unwrap_or(DEFAULT_DIR_MODE));
+
+    // Открыть папку для записи времени можно не на всех платформах, поэтому ошибка не критична
+    if let (Some(mtime), Ok(dir)) = (stat.mtime, File::open(local_path)) {
+        let _ = dir.set_times(file_times(stat.atime, mtime));
+    }
+
+    Ok(())
+}
+
+/// Загружает локальный файл или папку (рекурсивно) на сервер по SFTP,
+/// сохраняя права доступа и время изменения.
+pub fn run_upload(pool: &SessionPool, transfer_request: &LocalTransferRequest, summary: &mut TransferSummary) -> Result<String, AppError> {
+    let local_path = PathBuf::from(&transfer_request.local_path);
+
+    let metadata = fs::metadata(&local_path)
+        .map_err(|e| AppError::io(&Msg::LocalPathUnavailable.format(&[&transfer_request.local_path]), &e))?;
+
+    let sess = get_session(pool, &transfer_request.connection)?;
+
+    check_paths(&sess, &[(PolicyOperation::Write, &transfer_request.remote_path)], transfer_request.confirmed)?;
+
+    let endpoint = transfer_request.connection.endpoint();
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let policy = transfer_request.overwrite_policy;
+
+    if metadata.is_dir() {
+        upload_directory(&sftp, &endpoint, &local_path, &transfer_request.remote_path, policy, summary)?;
+        return Ok(summary.describe(Msg::FolderUploaded.format(&[&transfer_request.local_path])));
+    }
+
+    upload_file(&sftp, &endpoint, &local_path, &transfer_request.remote_path, policy, summary)?;
+
+    if summary.skipped > 0 {
+        return Ok(Msg::FileSkipped.format(&[&transfer_request.remote_path]));
+    }
+
+    Ok(Msg::FileUploaded.format(&[&transfer_request.local_path]))
+}
+
+/// Скачивает файл или папку (рекурсивно) с сервера по SFTP,
+/// сохраняя права доступа и время изменения.
+pub fn run_download(pool: &SessionPool, transfer_request: &LocalTransferRequest, summary: &mut TransferSummary) -> Result<String, AppError> {
+    let sess = get_session(pool, &transfer_request.connection)?;
+    let endpoint = transfer_request.connection.endpoint();
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let stat = sftp.stat(Path::new(&transfer_request.remote_path))
+        .map_err(|e| AppError::ssh(&Msg::RemotePathUnavailable.format(&[&transfer_request.remote_path]), &e))?;
+
+    let local_path = PathBuf::from(&transfer_request.local_path);
+    let policy = transfer_request.overwrite_policy;
+
+    if stat.is_dir() {
+        download_directory(&sftp, &endpoint, &transfer_request.remote_path, &local_path, policy, summary)?;
+        return Ok(summary.describe(Msg::FolderDownloaded.format(&[&transfer_request.remote_path])));
+    }
+
+    download_file(&sftp, &endpoint, &transfer_request.remote_path, &local_path, policy, summary)?;
+
+    if summary.skipped > 0 {
+        return Ok(Msg::FileSkipped.format(&[&transfer_request.local_path]));
+    }
+
+    Ok(Msg::FileDownloaded.format(&[&transfer_request.remote_path]))
+}
+
+#[command]
+pub fn upload_to_server(pool: State<'_, SessionPool>, transfer_request: LocalTransferRequest) -> Result<String, AppError> {
+    run_upload(&pool, &transfer_request, &mut TransferSummary::default())
+}
+
+#[command]
+pub fn download_from_server(pool: State<'_, SessionPool>, transfer_request: LocalTransferRequest) -> Result<String, AppError> {
+    run_download(&pool, &transfer_request, &mut TransferSummary::default())
+}
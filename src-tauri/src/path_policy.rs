@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use ssh2::{Session, Sftp};
+use serde_json::json;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::storage::load_app_settings;
+
+/// Имя правила для точек монтирования, найденных в /proc/mounts сервера.
+const MOUNT_POINT_RULE: &str = "mount_point";
+
+/// Корневые системные папки: удалять, переименовывать и перезаписывать их нельзя.
+const SYSTEM_ROOTS: &[&str] = &[
+    "/", "/bin", "/boot", "/dev", "/etc", "/home", "/lib", "/lib32", "/lib64", "/media", "/mnt",
+    "/opt", "/proc", "/root", "/run", "/sbin", "/srv", "/sys", "/tmp", "/usr", "/var",
+];
+
+/// Псевдофайловые системы ядра: запрещено все, что внутри.
+const KERNEL_FILESYSTEMS: &[&str] = &["/proc", "/sys", "/dev"];
+
+/// Домашние папки, содержимое системных папок и подключенные диски: только с подтверждением.
+const CONFIRMED_PATHS: &[&str] = &[
+    "~", "/home/*", "/etc/*", "/usr/*", "/var/*", "/opt/*", "/srv/*", "/boot/*", "/mnt/*", "/media/*", "/media/*/*",
+];
+
+/// Что операция делает с путем.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyOperation {
+    Delete,
+    Rename,
+    /// Запись поверх пути: назначение копирования, перемещения или загрузки
+    Write,
+}
+
+impl PolicyOperation {
+    fn label(self) -> &'static str {
+        match self {
+            PolicyOperation::Delete => Msg::OperationDelete.text(),
+            PolicyOperation::Rename => Msg::OperationRename.text(),
+            PolicyOperation::Write => Msg::OperationWrite.text(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Операция отклоняется всегда
+    Deny,
+    /// Операция выполняется только с подтверждением пользователя
+    Confirm,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathRule {
+    /// Абсолютный путь; `*` заменяет часть имени, `~` в начале — домашняя папка пользователя
+    pub pattern: String,
+    pub action: PolicyAction,
+    /// Правило действует и на все вложенные пути
+    #[serde(default)]
+    pub include_children: bool,
+    /// Операции, к которым относится правило; пустой список — ко всем
+    #[serde(default)]
+    pub operations: Vec<PolicyOperation>,
+}
+
+impl PathRule {
+    fn new(pattern: &str, action: PolicyAction, include_children: bool) -> Self {
+        PathRule {
+            pattern: pattern.to_string(),
+            action,
+            include_children,
+            operations: Vec::new(),
+        }
+    }
+
+    fn matches(&self, path: &str, operation: PolicyOperation, home: &str) -> bool {
+        if !self.operations.is_empty() && !self.operations.contains(&operation) {
+            return false;
+        }
+
+        let pattern = normalize_path(&self.pattern, home);
+        let pattern: Vec<&str> = segments(&pattern).collect();
+        let path: Vec<&str> = segments(path).collect();
+
+        if path.len() < pattern.len() || (path.len() > pattern.len() && !self.include_children) {
+            return false;
+        }
+
+        pattern.iter().zip(&path).all(|(pattern, name)| wildcard_match(pattern, name))
+    }
+}
+
+/// Какие пути защищены от удаления, переименования и перезаписи. Хранится в настройках приложения.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathPolicy {
+    #[serde(default = "default_rules")]
+    pub rules: Vec<PathRule>,
+    /// Запрещать операции над точками монтирования сервера
+    #[serde(default = "default_protect_mount_points")]
+    pub protect_mount_points: bool,
+}
+
+fn default_rules() -> Vec<PathRule> {
+    SYSTEM_ROOTS.iter().map(|path| PathRule::new(path, PolicyAction::Deny, false))
+        .chain(KERNEL_FILESYSTEMS.iter().map(|path| PathRule::new(path, PolicyAction::Deny, true)))
+        .chain(CONFIRMED_PATHS.iter().map(|path| PathRule::new(path, PolicyAction::Confirm, false)))
+        .collect()
+}
+
+fn default_protect_mount_points() -> bool {
+    true
+}
+
+impl Default for PathPolicy {
+    fn default() -> Self {
+        PathPolicy {
+            rules: default_rules(),
+            protect_mount_points: default_protect_mount_points(),
+        }
+    }
+}
+
+impl PathPolicy {
+    pub fn validate(&self) -> Result<(), AppError> {
+        for rule in &self.rules {
+            if !rule.pattern.starts_with('/') && !rule.pattern.starts_with('~') {
+                return Err(AppError::invalid_input(Msg::InvalidPolicyPattern.format(&[&rule.pattern])));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Проверяет уже нормализованный путь. Если путь подходит под несколько правил, запрет
+    /// сильнее подтверждения. В `details` ошибки — путь, операция и сработавшее правило;
+    /// после `confirmation_required` запрос повторяется с `confirmed: true`.
+    fn evaluate(&self, path: &str, operation: PolicyOperation, home: &str, mount_points: &[String], confirmed: bool) -> Result<(), AppError> {
+        let mut verdict = None;
+
+        if self.protect_mount_points && mount_points.iter().any(|mount_point| mount_point == path) {
+            verdict = Some((PolicyAction::Deny, MOUNT_POINT_RULE.to_string()));
+        }
+
+        for rule in self.rules.iter().filter(|rule| rule.matches(path, operation, home)) {
+            match verdict {
+                Some((PolicyAction::Deny, _)) => break,
+                Some((PolicyAction::Confirm, _)) if rule.action == PolicyAction::Confirm => {}
+                _ => verdict = Some((rule.action, rule.pattern.clone())),
+            }
+        }
+
+        let error = match verdict {
+            Some((PolicyAction::Deny, rule)) => AppError::new(
+                ErrorCode::PolicyDenied,
+                Msg::PathForbidden.format(&[&path, &operation.label(), &rule]),
+            )
+            .with_details(json!({ "path": path, "operation": operation, "rule": rule })),
+            Some((PolicyAction::Confirm, rule)) if !confirmed => AppError::new(
+                ErrorCode::ConfirmationRequired,
+                Msg::PathNeedsConfirmation.format(&[&path, &operation.label(), &rule]),
+            )
+            .with_details(json!({ "path": path, "operation": operation, "rule": rule })),
+            _ => return Ok(()),
+        };
+
+        Err(error)
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+
+            (0..=name.len())
+                .filter(|&index| name.is_char_boundary(index))
+                .any(|index| wildcard_match(rest, &name[index..]))
+        }
+    }
+}
+
+/// Абсолютный путь без `.`, `..` и повторных `/`. Относительные пути и `~` отсчитываются
+/// от домашней папки, как в SFTP и командах на сервере.
+fn normalize_path(path: &str, home: &str) -> String {
+    let absolute = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ if path.starts_with('/') => path.to_string(),
+        _ => format!("{}/{}", home, path),
+    };
+
+    let mut normalized: Vec<&str> = Vec::new();
+
+    for segment in segments(&absolute) {
+        match segment {
+            "." => {}
+            ".." => {
+                normalized.pop();
+            }
+            segment => normalized.push(segment),
+        }
+    }
+
+    format!("/{}", normalized.join("/"))
+}
+
+/// Пути в /proc/mounts записаны с восьмеричными экранами: `\040` — пробел.
+fn unescape_mount_path(path: &str) -> String {
+    let raw = path.as_bytes();
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut index = 0;
+
+    while index < raw.len() {
+        let escaped = raw.get(index + 1..index + 4)
+            .filter(|digits| raw[index] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit)))
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                index += 4;
+            }
+            None => {
+                bytes.push(raw[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn parse_mount_points(mounts: &str) -> Vec<String> {
+    mounts
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(unescape_mount_path)
+        .collect()
+}
+
+/// Точки монтирования сервера; на системах без /proc список пуст.
+fn read_mount_points(sftp: &Sftp) -> Vec<String> {
+    let mut mounts = String::new();
+
+    match sftp.open(Path::new("/proc/mounts")).map(|mut file| file.read_to_string(&mut mounts)) {
+        Ok(Ok(_)) => parse_mount_points(&mounts),
+        _ => Vec::new(),
+    }
+}
+
+/// Проверяет пути по политике из настроек перед разрушающей операцией.
+pub fn check_paths(sess: &Session, checks: &[(PolicyOperation, &str)], confirmed: bool) -> Result<(), AppError> {
+    let policy = load_app_settings()?.path_policy;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let home = sftp.realpath(Path::new("."))
+        .map(|path| path.to_string_lossy().into_owned())
+        .map_err(|e| AppError::ssh(Msg::HomeDirResolveFailed.text(), &e))?;
+
+    let mount_points = if policy.protect_mount_points {
+        read_mount_points(&sftp)
+    } else {
+        Vec::new()
+    };
+
+    for (operation, path) in checks {
+        policy.evaluate(&normalize_path(path, &home), *operation, &home, &mount_points, confirmed)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: &str = "/home/admin";
+
+    /// Код ошибки и сработавшее правило или `None`, если операция разрешена.
+    fn evaluate(path: &str, confirmed: bool) -> Option<(ErrorCode, String)> {
+        let mount_points = vec!["/".to_string(), "/mnt/backup disk".to_string()];
+
+        PathPolicy::default()
+            .evaluate(&normalize_path(path, HOME), PolicyOperation::Delete, HOME, &mount_points, confirmed)
+            .err()
+            .map(|error| (error.code, error.details["rule"].as_str().unwrap_or_default().to_string()))
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize_path("/etc/nginx/../", HOME), "/etc");
+        assert_eq!(normalize_path("//var/./log", HOME), "/var/log");
+        assert_eq!(normalize_path("~", HOME), HOME);
+        assert_eq!(normalize_path("projects/..", HOME), HOME);
+        assert_eq!(normalize_path("/../..", HOME), "/");
+    }
+
+    #[test]
+    fn default_policy_protects_system_paths() {
+        assert_eq!(evaluate("/etc/", true), Some((ErrorCode::PolicyDenied, "/etc".to_string())));
+        assert_eq!(evaluate("/proc/1/fd", true), Some((ErrorCode::PolicyDenied, "/proc".to_string())));
+        assert_eq!(evaluate("/etc/nginx", false), Some((ErrorCode::ConfirmationRequired, "/etc/*".to_string())));
+        assert_eq!(evaluate("projects/..", false), Some((ErrorCode::ConfirmationRequired, "~".to_string())));
+        assert_eq!(evaluate("/etc/nginx", true), None);
+        assert_eq!(evaluate("/etc/nginx/sites-enabled/default", false), None);
+    }
+
+    #[test]
+    fn denies_mount_points() {
+        let mount_points = parse_mount_points("/dev/sdb1 /mnt/backup\\040disk ext4 rw,relatime 0 0\n");
+        assert_eq!(mount_points, vec!["/mnt/backup disk"]);
+
+        assert_eq!(evaluate("/mnt/backup disk", true), Some((ErrorCode::PolicyDenied, MOUNT_POINT_RULE.to_string())));
+    }
+
+    #[test]
+    fn rules_can_target_operations() {
+        let policy = PathPolicy {
+            rules: vec![PathRule {
+                pattern: "/srv/www/*.conf".to_string(),
+                action: PolicyAction::Deny,
+                include_children: false,
+                operations: vec![PolicyOperation::Write],
+            }],
+            protect_mount_points: false,
+        };
+
+        assert!(policy.evaluate("/srv/www/site.conf", PolicyOperation::Write, HOME, &[], true).is_err());
+        assert!(policy.evaluate("/srv/www/site.conf", PolicyOperation::Delete, HOME, &[], true).is_ok());
+        assert!(policy.evaluate("/srv/www/site.confd", PolicyOperation::Write, HOME, &[], true).is_ok());
+    }
+}
@@ -0,0 +1,173 @@
+use ssh2::{Channel, Session};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::remote_command::{exec, CommandOutput, RemoteCommand};
+use crate::storage::SudoMode;
+
+/// Начало собственного приглашения sudo (`-p`), чтобы надежно отличить его от вывода команды.
+/// За ним идет номер команды в цепочке: повтор того же номера значит, что пароль не подошел.
+const SUDO_PROMPT: &str = "[ssh-connect] sudo password #";
+
+impl SudoMode {
+    /// Пароль для sudo или `None`, если он не нужен (NOPASSWD).
+    fn password<'a>(&'a self, login_password: &'a str) -> Option<&'a str> {
+        match self {
+            SudoMode::LoginPassword => Some(login_password),
+            SudoMode::Password { password } => Some(password),
+            SudoMode::NoPassword => None,
+        }
+    }
+}
+
+/// `sudo` с нашим приглашением или `sudo -n` для NOPASSWD перед каждой командой цепочки,
+/// без обертки в `sh -c`, чтобы работали правила sudoers для конкретных команд.
+/// Пароль в командную строку не попадает.
+fn sudo_command(mode: &SudoMode, command: &RemoteCommand) -> RemoteCommand {
+    command.each_prefixed(|index| {
+        let sudo = match mode {
+            SudoMode::NoPassword => RemoteCommand::new("sudo").arg("-n"),
+            _ => RemoteCommand::new("sudo").arg("-S").arg("-p").arg(format!("{}{}:", SUDO_PROMPT, index + 1)),
+        };
+        sudo.arg("--")
+    })
+}
+
+/// Выполняет команду с правами администратора. Пароль передается в stdin sudo
+/// только в ответ на его приглашение.
+pub fn run_privileged(sess: &Session, mode: &SudoMode, login_password: &str, command: &RemoteCommand) -> Result<CommandOutput, AppError> {
+    let mut channel = sess.channel_session()
+        .map_err(|e| AppError::ssh(Msg::ChannelOpenFailed.text(), &e))?;
+
+    exec(&mut channel, &sudo_command(mode, command))
+        .map_err(|e| AppError::ssh(Msg::CommandExecFailed.text(), &e))?;
+
+    let stderr = read_stderr(&mut channel, mode.password(login_password))?;
+
+    // Команды под sudo почти ничего не пишут в stdout, а до размера окна канала его буферизует libssh2
+    let mut stdout = String::new();
+    channel.read_to_string(&mut stdout)
+        .map_err(|e| AppError::io(Msg::CommandOutputReadFailed.text(), &e))?;
+
+    channel.wait_close()
+        .map_err(|e| AppError::ssh(Msg::ChannelCloseFailed.text(), &e))?;
+
+    let exit_status = channel.exit_status().unwrap_or(-1);
+
+    if let Some(error) = sudo_failure(&stderr, exit_status) {
+        return Err(error);
+    }
+
+    Ok(CommandOutput {
+        stdout,
+        stderr,
+        exit_status,
+    })
+}
+
+/// Читает stderr до конца, отвечая паролем на приглашения sudo и вырезая их из вывода.
+fn read_stderr(channel: &mut Channel, password: Option<&str>) -> Result<String, AppError> {
+    let mut stderr = String::new();
+    let mut buffer = [0u8; 1024];
+    let mut answered = HashSet::new();
+
+    loop {
+        while let Some((range, index)) = find_prompt(&stderr) {
+            stderr.replace_range(range, "");
+
+            // sudo повторяет приглашение той же команды, если пароль не подошел
+            if !answered.insert(index) {
+                let _ = channel.close();
+                return Err(AppError::new(ErrorCode::AuthFailed, Msg::SudoWrongPassword.text()));
+            }
+
+            let password = match password {
+                Some(password) if !password.is_empty() => password,
+                _ => {
+                    let _ = channel.close();
+                    return Err(AppError::new(ErrorCode::AuthFailed, Msg::SudoPasswordMissing.text()));
+                }
+            };
+
+            channel.write_all(format!("{}\n", password).as_bytes())
+                .and_then(|_| channel.flush())
+                .map_err(|e| AppError::io(Msg::SudoPasswordSendFailed.text(), &e))?;
+        }
+
+        let read = channel.stderr().read(&mut buffer)
+            .map_err(|e| AppError::io(Msg::SudoOutputReadFailed.text(), &e))?;
+
+        if read == 0 {
+            return Ok(stderr);
+        }
+
+        stderr.push_str(&String::from_utf8_lossy(&buffer[..read]));
+    }
+}
+
+/// Полностью полученное приглашение sudo: его место в тексте и номер команды.
+fn find_prompt(stderr: &str) -> Option<(Range<usize>, usize)> {
+    let start = stderr.find(SUDO_PROMPT)?;
+    let number_start = start + SUDO_PROMPT.len();
+    let number_end = number_start + stderr[number_start..].find(':')?;
+    let index = stderr[number_start..number_end].parse().ok()?;
+
+    Some((start..number_end + 1, index))
+}
+
+/// Отказ самого sudo (а не выполняемой команды) превращается в понятную ошибку.
+fn sudo_failure(stderr: &str, exit_status: i32) -> Option<AppError> {
+    if exit_status == 0 {
+        return None;
+    }
+
+    if stderr.contains("a password is required") {
+        return Some(AppError::new(ErrorCode::AuthFailed, Msg::SudoPasswordRequired.text()));
+    }
+
+    if stderr.contains("is not in the sudoers file") || stderr.contains("is not allowed to execute") {
+        return Some(AppError::new(ErrorCode::PermissionDenied, Msg::SudoNotAllowed.format(&[&stderr.trim()])));
+    }
+
+    if stderr.contains("sudo: command not found") || stderr.contains("sudo: not found") {
+        return Some(AppError::new(ErrorCode::Unsupported, Msg::SudoNotInstalled.text()));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_mode_runs_command_directly_with_own_prompt() {
+        let command = sudo_command(&SudoMode::LoginPassword, &RemoteCommand::new("rm").arg("/x y"));
+        assert_eq!(command.as_str(), "sudo '-S' '-p' '[ssh-connect] sudo password #1:' '--' rm '/x y'");
+    }
+
+    #[test]
+    fn every_command_in_chain_gets_own_sudo() {
+        let chain = RemoteCommand::new("mkdir").arg("/a").and(RemoteCommand::new("mv").arg("/b").arg("/a/b"));
+        assert_eq!(
+            sudo_command(&SudoMode::NoPassword, &chain).as_str(),
+            "sudo '-n' '--' mkdir '/a' && sudo '-n' '--' mv '/b' '/a/b'"
+        );
+    }
+
+    #[test]
+    fn finds_complete_prompt_only() {
+        assert_eq!(find_prompt("lecture\n[ssh-connect] sudo password #2:"), Some((8..39, 2)));
+        assert_eq!(find_prompt("[ssh-connect] sudo password #1"), None);
+    }
+
+    #[test]
+    fn password_is_never_part_of_command_line() {
+        let mode = SudoMode::Password { password: "s3cr'et".to_string() };
+        let command = sudo_command(&mode, &RemoteCommand::new("true"));
+        assert!(!command.as_str().contains("s3cr"));
+    }
+}
@@ -0,0 +1,189 @@
+use ssh2::{Channel, Session};
+use std::io::Read;
+
+use crate::error::{is_permission_denied, AppError};
+use crate::i18n::Msg;
+
+/// Команда для выполнения на сервере.
+///
+/// Имя программы и операторы shell задаются только статическими строками, а каждый аргумент
+/// экранируется одинарными кавычками. Поэтому путь с `;`, `$(...)` или кавычками не может
+/// превратиться в отдельную команду. `exec` и `run_command` — единственные места, где
+/// вызывается `Channel::exec` (остальное запрещено через clippy.toml).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteCommand {
+    script: String,
+    /// Где в `script` начинается каждая простая команда цепочки
+    starts: Vec<usize>,
+}
+
+impl RemoteCommand {
+    pub fn new(program: &'static str) -> Self {
+        RemoteCommand {
+            script: program.to_string(),
+            starts: vec![0],
+        }
+    }
+
+    /// Добавляет аргумент, экранированный для POSIX shell.
+    pub fn arg<S: AsRef<str>>(mut self, arg: S) -> Self {
+        self.script.push(' ');
+        self.script.push_str(&shell_quote(arg.as_ref()));
+        self
+    }
+
+    /// `self && next`
+    pub fn and(self, next: RemoteCommand) -> Self {
+        self.join("&&", next)
+    }
+
+    /// `self || next`
+    pub fn or(self, next: RemoteCommand) -> Self {
+        self.join("||", next)
+    }
+
+    /// `self; next`
+    pub fn then(self, next: RemoteCommand) -> Self {
+        self.join(";", next)
+    }
+
+    /// Подавляет stderr последней команды в цепочке.
+    pub fn stderr_to_null(mut self) -> Self {
+        self.script.push_str(" 2>/dev/null");
+        self
+    }
+
+    /// Ставит перед каждой простой командой цепочки свой префикс: `a && b` -> `sudo a && sudo b`.
+    /// `prefix` получает номер команды в цепочке, начиная с нуля.
+    pub fn each_prefixed(&self, prefix: impl Fn(usize) -> RemoteCommand) -> Self {
+        let mut script = String::new();
+        let mut starts = Vec::with_capacity(self.starts.len());
+        let mut copied = 0;
+
+        for (index, &start) in self.starts.iter().enumerate() {
+            script.push_str(&self.script[copied..start]);
+            starts.push(script.len());
+            script.push_str(&prefix(index).script);
+            script.push(' ');
+            copied = start;
+        }
+        script.push_str(&self.script[copied..]);
+
+        RemoteCommand { script, starts }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.script
+    }
+
+    fn join(mut self, operator: &'static str, next: RemoteCommand) -> Self {
+        if operator != ";" {
+            self.script.push(' ');
+        }
+        self.script.push_str(operator);
+        self.script.push(' ');
+
+        let offset = self.script.len();
+        self.starts.extend(next.starts.iter().map(|start| start + offset));
+        self.script.push_str(&next.script);
+        self
+    }
+}
+
+/// Экранирует строку для POSIX shell: `it's` -> `'it'"'"'s'`.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\"'\"'"))
+}
+
+/// Результат выполнения команды.
+#[derive(Debug)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_status: i32,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_status == 0
+    }
+
+    /// Отказ в доступе, включая `Operation not permitted` от chown/chmod для чужих файлов.
+    pub fn permission_denied(&self) -> bool {
+        is_permission_denied(&self.stderr)
+    }
+
+    /// Ошибка неудачной команды: `"{context} (код N): stderr"` с кодом по stderr.
+    pub fn error(&self, context: &str) -> AppError {
+        AppError::command(context, self.exit_status, &self.stderr)
+    }
+}
+
+/// Запускает команду в уже открытом канале (для случаев, когда нужен потоковый доступ к каналу).
+#[allow(clippy::disallowed_methods)]
+pub fn exec(channel: &mut Channel, command: &RemoteCommand) -> Result<(), ssh2::Error> {
+    channel.exec(command.as_str())
+}
+
+/// Выполняет команду в новом канале и собирает stdout, stderr и код завершения.
+pub fn run_command(sess: &Session, command: &RemoteCommand) -> Result<CommandOutput, AppError> {
+    let mut channel = sess.channel_session()
+        .map_err(|e| AppError::ssh(Msg::ChannelOpenFailed.text(), &e))?;
+
+    exec(&mut channel, command)
+        .map_err(|e| AppError::ssh(Msg::CommandExecFailed.text(), &e))?;
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+
+    channel.read_to_string(&mut stdout)
+        .map_err(|e| AppError::io(Msg::CommandOutputReadFailed.text(), &e))?;
+    let _ = channel.stderr().read_to_string(&mut stderr);
+
+    channel.wait_close()
+        .map_err(|e| AppError::ssh(Msg::ChannelCloseFailed.text(), &e))?;
+
+    let exit_status = channel.exit_status().unwrap_or(-1);
+
+    Ok(CommandOutput {
+        stdout,
+        stderr,
+        exit_status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_plain_arguments() {
+        let command = RemoteCommand::new("ls").arg("-la").arg("/var/log");
+        assert_eq!(command.as_str(), "ls '-la' '/var/log'");
+    }
+
+    #[test]
+    fn shell_metacharacters_stay_inside_argument() {
+        let command = RemoteCommand::new("ls").arg("/tmp; rm -rf / $(reboot) `id` && echo");
+        assert_eq!(command.as_str(), "ls '/tmp; rm -rf / $(reboot) `id` && echo'");
+    }
+
+    #[test]
+    fn escapes_single_quotes() {
+        let command = RemoteCommand::new("cat").arg("it's");
+        assert_eq!(command.as_str(), "cat 'it'\"'\"'s'");
+    }
+
+    #[test]
+    fn joins_commands_with_operators() {
+        let command = RemoteCommand::new("test").arg("-d").arg("/a")
+            .or(RemoteCommand::new("mkdir").arg("-p").arg("/a"))
+            .then(RemoteCommand::new("echo").arg("--"))
+            .and(RemoteCommand::new("stat").arg("/a").stderr_to_null());
+
+        assert_eq!(
+            command.as_str(),
+            "test '-d' '/a' || mkdir '-p' '/a'; echo '--' && stat '/a' 2>/dev/null"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use ssh2::Session;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{command, State};
+
+use crate::error::AppError;
+use crate::i18n::Msg;
+
+// Сессия, которой не пользовались дольше этого времени, закрывается
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+// Как часто проверять, что соединение еще живо, перед выдачей сессии
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const HEALTH_CHECK_TIMEOUT_MS: u32 = 10_000;
+const KEEPALIVE_INTERVAL_SECS: u32 = 30;
+const REAPER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Ключ сессии в пуле: ID сервера (или строка подключения, если ID не передан)
+/// и отпечаток учетных данных, чтобы после смены пароля не использовать старую сессию.
+pub struct SessionKey {
+    id: String,
+    credentials: u64,
+}
+
+impl SessionKey {
+    pub fn new<C: Hash>(server_id: Option<u32>, username: &str, host: &str, credentials: &C) -> Self {
+        let id = match server_id {
+            Some(id) => id.to_string(),
+            None => format!("{}|{}", username, host),
+        };
+
+        let mut hasher = DefaultHasher::new();
+        credentials.hash(&mut hasher);
+
+        SessionKey {
+            id,
+            credentials: hasher.finish(),
+        }
+    }
+
+    /// Ключ отдельной сессии того же сервера с именем `name`: ее не делят с другими командами.
+    pub fn dedicated(&self, name: &str) -> SessionKey {
+        SessionKey {
+            id: format!("{}#{}", self.id, name),
+            credentials: self.credentials,
+        }
+    }
+}
+
+/// Сколько команд сейчас работают с сессией и когда она использовалась последний раз.
+struct Usage {
+    leases: AtomicUsize,
+    last_used: Mutex<Instant>,
+}
+
+impl Usage {
+    fn new() -> Self {
+        Usage {
+            leases: AtomicUsize::new(0),
+            last_used: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Instant::now();
+    }
+
+    fn is_leased(&self) -> bool {
+        self.leases.load(Ordering::Acquire) > 0
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.is_leased()
+            && self.last_used.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).elapsed() >= IDLE_TIMEOUT
+    }
+}
+
+/// Сессия, выданная из пула. Пока она не освобождена, пул не закрывает сессию по простою,
+/// поэтому долгие передачи и `tail -f` не обрываются; освобождение продлевает срок простоя.
+pub struct SessionLease {
+    session: Session,
+    usage: Arc<Usage>,
+}
+
+impl SessionLease {
+    fn new(session: Session, usage: Arc<Usage>) -> Self {
+        usage.leases.fetch_add(1, Ordering::AcqRel);
+        usage.touch();
+        SessionLease { session, usage }
+    }
+}
+
+impl Deref for SessionLease {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl Drop for SessionLease {
+    fn drop(&mut self) {
+        self.usage.touch();
+        self.usage.leases.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+struct PooledSession {
+    session: Session,
+    credentials: u64,
+    usage: Arc<Usage>,
+    last_checked: Instant,
+}
+
+impl PooledSession {
+    /// Закрывает сессию, если ею никто не пользуется. Иначе соединение закроется,
+    /// когда последняя команда освободит сессию.
+    fn close(self, reason: &str) {
+        if !self.usage.is_leased() {
+            close_session(&self.session, reason);
+        }
+    }
+}
+
+/// Реестр открытых SSH-сессий, общий для всех команд (хранится в состоянии Tauri).
+#[derive(Default)]
+pub struct SessionPool {
+    sessions: Arc<Mutex<HashMap<String, PooledSession>>>,
+}
+
+impl SessionPool {
+    /// Возвращает живую сессию для ключа, при необходимости переподключаясь через `connect`.
+    pub fn get<F>(&self, key: &SessionKey, connect: F) -> Result<SessionLease, AppError>
+    where
+        F: FnOnce() -> Result<Session, AppError>,
+    {
+        if let Some(lease) = self.take_live(key) {
+            return Ok(lease);
+        }
+
+        let session = connect()?;
+        session.set_keepalive(true, KEEPALIVE_INTERVAL_SECS);
+
+        let usage = Arc::new(Usage::new());
+        let lease = SessionLease::new(session.clone(), usage.clone());
+
+        let previous = self.lock().insert(
+            key.id.clone(),
+            PooledSession {
+                session,
+                credentials: key.credentials,
+                usage,
+                last_checked: Instant::now(),
+            },
+        );
+
+        if let Some(previous) = previous {
+            previous.close(Msg::SessionReplaced.text());
+        }
+
+        Ok(lease)
+    }
+
+    /// Убирает сессию из пула и закрывает ее, если ею больше никто не пользуется.
+    pub fn close(&self, key: &SessionKey, reason: &str) {
+        let removed = self.lock().remove(&key.id);
+
+        if let Some(pooled) = removed {
+            pooled.close(reason);
+        }
+    }
+
+    fn remove(&self, id: &str) -> bool {
+        let removed = self.lock().remove(id);
+
+        match removed {
+            Some(pooled) => {
+                pooled.close(Msg::SessionClosed.text());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take_live(&self, key: &SessionKey) -> Option<SessionLease> {
+        let lease = {
+            let mut sessions = self.lock();
+            let pooled = sessions.get_mut(&key.id)?;
+
+            if pooled.credentials != key.credentials {
+                let stale = sessions.remove(&key.id)?;
+                stale.close(Msg::CredentialsChanged.text());
+                return None;
+            }
+
+            let lease = SessionLease::new(pooled.session.clone(), pooled.usage.clone());
+
+            if pooled.last_checked.elapsed() < HEALTH_CHECK_INTERVAL {
+                return Some(lease);
+            }
+
+            lease
+        };
+
+        // Проверка выполняется без блокировки пула, чтобы не задерживать другие серверы.
+        // За это время другой поток мог заменить сессию, поэтому запись сверяется по `usage`
+        let alive = is_alive(&lease);
+        let mut sessions = self.lock();
+        let same = sessions.get(&key.id).is_some_and(|pooled| Arc::ptr_eq(&pooled.usage, &lease.usage));
+
+        if alive {
+            if let Some(pooled) = sessions.get_mut(&key.id).filter(|_| same) {
+                pooled.last_checked = Instant::now();
+            }
+            return Some(lease);
+        }
+
+        let dead = if same { sessions.remove(&key.id) } else { None };
+        drop(sessions);
+        drop(lease);
+
+        if let Some(dead) = dead {
+            dead.close(Msg::SessionClosed.text());
+        }
+        None
+    }
+
+    fn evict_idle(&self) {
+        let expired: Vec<PooledSession> = {
+            let mut sessions = self.lock();
+            let expired_ids: Vec<String> = sessions
+                .iter()
+                .filter(|(_, pooled)| pooled.usage.is_idle())
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            expired_ids
+                .iter()
+                .filter_map(|id| sessions.remove(id))
+                .collect()
+        };
+
+        for pooled in expired {
+            pooled.close(Msg::SessionIdleTimeout.text());
+        }
+    }
+
+    /// Запускает фоновый поток, закрывающий простаивающие сессии.
+    pub fn start_reaper(&self) {
+        let pool = SessionPool {
+            sessions: Arc::clone(&self.sessions),
+        };
+
+        thread::spawn(move || loop {
+            thread::sleep(REAPER_INTERVAL);
+            pool.evict_idle();
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, PooledSession>> {
+        // Пул не содержит инвариантов, которые могла бы нарушить паника другого потока
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn is_alive(session: &Session) -> bool {
+    if !session.authenticated() {
+        return false;
+    }
+
+    // Сессией параллельно пользуются другие команды: их таймаут возвращается после проверки
+    let timeout = session.timeout();
+    session.set_timeout(HEALTH_CHECK_TIMEOUT_MS);
+    let alive = session.keepalive_send().is_ok() && session.channel_session().is_ok();
+    session.set_timeout(timeout);
+
+    alive
+}
+
+fn close_session(session: &Session, reason: &str) {
+    let _ = session.disconnect(None, reason, None);
+}
+
+#[command]
+pub fn disconnect_server(pool: State<'_, SessionPool>, server_id: u32) -> Result<String, AppError> {
+    if pool.remove(&server_id.to_string()) {
+        Ok(Msg::ServerSessionClosed.format(&[&server_id]))
+    } else {
+        Ok(Msg::NoServerSession.format(&[&server_id]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leased_session_is_never_idle() {
+        let usage = Arc::new(Usage::new());
+        let long_ago = Instant::now().checked_sub(IDLE_TIMEOUT * 2).unwrap();
+        *usage.last_used.lock().unwrap() = long_ago;
+        assert!(usage.is_idle());
+
+        let lease = SessionLease::new(Session::new().unwrap(), usage.clone());
+        *usage.last_used.lock().unwrap() = long_ago;
+        assert!(!usage.is_idle());
+
+        // Освобождение продлевает срок простоя
+        drop(lease);
+        assert!(!usage.is_leased());
+        assert!(!usage.is_idle());
+    }
+}
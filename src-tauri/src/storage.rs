@@ -1,7 +1,40 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use tauri::command;
+use tauri::{command, State};
+
+use crate::connection_target::DEFAULT_SSH_PORT;
+use crate::error::AppError;
+use crate::i18n::{set_language, Language, Msg};
+use crate::path_policy::PathPolicy;
+use crate::vault::{is_encrypted, vault_initialized, Vault};
+
+/// Способ входа на сервер. Старые конфигурации без этого поля используют пароль.
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthMethod {
+    #[default]
+    Password,
+    PublicKey {
+        private_key_path: String,
+        #[serde(default)]
+        passphrase: Option<String>,
+    },
+    Agent,
+}
+
+/// Как получать права администратора через sudo.
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SudoMode {
+    /// Пароль sudo совпадает с паролем входа
+    #[default]
+    LoginPassword,
+    /// Отдельный пароль sudo
+    Password { password: String },
+    /// NOPASSWD в sudoers: пароль не запрашивается
+    NoPassword,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
@@ -9,46 +42,107 @@ pub struct ServerConfig {
     pub title: String,
     pub user: String,
     pub password: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub auth_method: AuthMethod,
+    #[serde(default)]
+    pub sudo: SudoMode,
+    /// Папка корзины на сервере (`~/` — домашняя папка). Если не задана, удаление необратимо
+    #[serde(default)]
+    pub trash_dir: Option<String>,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_SSH_PORT
 }
 
-fn get_config_dir() -> Result<PathBuf, String> {
+pub const DEFAULT_TRANSFER_CONCURRENCY: usize = 2;
+
+fn default_transfer_concurrency() -> usize {
+    DEFAULT_TRANSFER_CONCURRENCY
+}
+
+/// Общие настройки приложения, хранятся в settings.json рядом с конфигурацией серверов.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppSettings {
+    /// Дополнительно проверять ключи хостов по ~/.ssh/known_hosts пользователя
+    #[serde(default)]
+    pub use_system_known_hosts: bool,
+    /// Сколько фоновых передач файлов выполняется одновременно; остальные ждут в очереди
+    #[serde(default = "default_transfer_concurrency")]
+    pub transfer_concurrency: usize,
+    /// Защищенные пути: что нельзя удалять, переименовывать и перезаписывать без подтверждения
+    #[serde(default)]
+    pub path_policy: PathPolicy,
+    /// Язык сообщений; если не задан, берется из локали системы
+    #[serde(default)]
+    pub language: Option<Language>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            use_system_known_hosts: false,
+            transfer_concurrency: DEFAULT_TRANSFER_CONCURRENCY,
+            path_policy: PathPolicy::default(),
+            language: None,
+        }
+    }
+}
+
+pub fn get_config_dir() -> Result<PathBuf, AppError> {
     let home_dir = std::env::var("HOME")
         .or_else(|_| std::env::var("USERPROFILE"))
-        .map_err(|_| "Не удалось определить домашнюю директорию")?;
+        .map_err(|_| AppError::internal(Msg::HomeDirUnknown.text()))?;
     
     let mut path = PathBuf::from(home_dir);
     path.push(".ssh-connect");
     
     if !path.exists() {
         fs::create_dir_all(&path)
-            .map_err(|e| format!("Ошибка создания директории {}: {}", path.display(), e))?;
+            .map_err(|e| AppError::io(&Msg::ConfigDirCreateFailed.format(&[&path.display()]), &e))?;
     }
     
     Ok(path)
 }
 
-fn get_config_file_path() -> Result<PathBuf, String> {
+fn get_config_file_path() -> Result<PathBuf, AppError> {
     let mut config_dir = get_config_dir()?;
     config_dir.push("servers.json");
     Ok(config_dir)
 }
 
-fn ensure_config_file_exists() -> Result<PathBuf, String> {
+/// Копия servers.json на время смены мастер-пароля.
+fn get_servers_backup_path() -> Result<PathBuf, AppError> {
+    let mut config_dir = get_config_dir()?;
+    config_dir.push("servers.json.bak");
+    Ok(config_dir)
+}
+
+fn get_settings_file_path() -> Result<PathBuf, AppError> {
+    let mut config_dir = get_config_dir()?;
+    config_dir.push("settings.json");
+    Ok(config_dir)
+}
+
+fn ensure_config_file_exists() -> Result<PathBuf, AppError> {
     let config_path = get_config_file_path()?;
     
     if !config_path.exists() {
         fs::write(&config_path, "[]")
-            .map_err(|e| format!("Ошибка создания файла конфигурации: {}", e))?;
+            .map_err(|e| AppError::io(Msg::ConfigCreateFailed.text(), &e))?;
     }
     
     Ok(config_path)
 }
 
 #[command]
-pub fn add_server_to_config(title: String, user: String, password: String) -> Result<ServerConfig, String> {
+#[allow(clippy::too_many_arguments)]
+pub fn add_server_to_config(vault: State<'_, Vault>, title: String, user: String, password: String, port: Option<u16>, auth_method: Option<AuthMethod>, sudo: Option<SudoMode>, trash_dir: Option<String>) -> Result<ServerConfig, AppError> {
     ensure_config_file_exists()?;
     
-    let mut servers = load_servers_from_file()?;
+    let mut servers = load_servers_from_file(&vault)?;
     
     let new_id = servers.iter().map(|s| s.id).max().unwrap_or(0) + 1;
     
@@ -57,84 +151,313 @@ pub fn add_server_to_config(title: String, user: String, password: String) -> Re
         title,
         user,
         password,
+        port: port.unwrap_or(DEFAULT_SSH_PORT),
+        auth_method: auth_method.unwrap_or_default(),
+        sudo: sudo.unwrap_or_default(),
+        trash_dir: trash_dir.filter(|dir| !dir.trim().is_empty()),
     };
     
     servers.push(new_server.clone());
-    save_servers_to_file(&servers)?;
+    save_servers_to_file(&vault, &servers)?;
     
     Ok(new_server)
 }
 
 #[command]
-pub fn update_server_in_config(id: u32, title: String, user: String, password: String) -> Result<ServerConfig, String> {
-    let mut servers = load_servers_from_file()?;
+#[allow(clippy::too_many_arguments)]
+pub fn update_server_in_config(vault: State<'_, Vault>, id: u32, title: String, user: String, password: String, port: Option<u16>, auth_method: Option<AuthMethod>, sudo: Option<SudoMode>, trash_dir: Option<String>) -> Result<ServerConfig, AppError> {
+    let mut servers = load_servers_from_file(&vault)?;
     
     let updated_server = ServerConfig {
         id,
         title,
         user,
         password,
+        port: port.unwrap_or(DEFAULT_SSH_PORT),
+        auth_method: auth_method.unwrap_or_default(),
+        sudo: sudo.unwrap_or_default(),
+        trash_dir: trash_dir.filter(|dir| !dir.trim().is_empty()),
     };
     
     let server_index = servers.iter().position(|s| s.id == id)
-        .ok_or_else(|| format!("Сервер с ID {} не найден", id))?;
+        .ok_or_else(|| AppError::not_found(Msg::ServerNotFound.format(&[&id])))?;
     
     servers[server_index] = updated_server.clone();
-    save_servers_to_file(&servers)?;
+    save_servers_to_file(&vault, &servers)?;
     
     Ok(updated_server)
 }
 
 #[command]
-pub fn remove_server_from_config(id: u32) -> Result<String, String> {
-    let mut servers = load_servers_from_file()?;
+pub fn remove_server_from_config(vault: State<'_, Vault>, id: u32) -> Result<String, AppError> {
+    let mut servers = load_servers_from_file(&vault)?;
     
     let initial_len = servers.len();
     servers.retain(|s| s.id != id);
     
     if servers.len() == initial_len {
-        return Err(format!("Сервер с ID {} не найден", id));
+        return Err(AppError::not_found(Msg::ServerNotFound.format(&[&id])));
     }
     
-    save_servers_to_file(&servers)?;
-    Ok(format!("Сервер с ID {} удален", id))
+    save_servers_to_file(&vault, &servers)?;
+    Ok(Msg::ServerRemoved.format(&[&id]))
 }
 
 #[command]
-pub fn load_servers_from_config() -> Result<Vec<ServerConfig>, String> {
-    load_servers_from_file()
+pub fn load_servers_from_config(vault: State<'_, Vault>) -> Result<Vec<ServerConfig>, AppError> {
+    load_servers_from_file(&vault)
 }
 
 #[command]
-pub fn get_config_path() -> Result<String, String> {
+pub fn get_config_path() -> Result<String, AppError> {
     let config_path = get_config_file_path()?;
     Ok(config_path.to_string_lossy().to_string())
 }
 
-fn load_servers_from_file() -> Result<Vec<ServerConfig>, String> {
+fn read_servers_file() -> Result<Vec<ServerConfig>, AppError> {
     let config_path = ensure_config_file_exists()?;
     
     let json_data = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Ошибка чтения файла конфигурации: {}", e))?;
+        .map_err(|e| AppError::io(Msg::ConfigReadFailed.text(), &e))?;
     
     if json_data.trim().is_empty() {
         return Ok(vec![]);
     }
     
     let servers: Vec<ServerConfig> = serde_json::from_str(&json_data)
-        .map_err(|e| format!("Ошибка парсинга конфигурации: {}", e))?;
+        .map_err(|e| AppError::other(Msg::ConfigParseFailed.text(), e))?;
     
     Ok(servers)
 }
 
-fn save_servers_to_file(servers: &[ServerConfig]) -> Result<(), String> {
+fn write_servers_file(servers: &[ServerConfig]) -> Result<(), AppError> {
     let config_path = ensure_config_file_exists()?;
     
     let json_data = serde_json::to_string_pretty(servers)
-        .map_err(|e| format!("Ошибка сериализации конфигурации: {}", e))?;
+        .map_err(|e| AppError::other(Msg::ConfigSerializeFailed.text(), e))?;
     
-    fs::write(&config_path, json_data)
-        .map_err(|e| format!("Ошибка записи файла конфигурации: {}", e))?;
+    // Через временный файл, чтобы сбой во время записи не оставил конфигурацию недописанной
+    let temp_path = config_path.with_extension("json.tmp");
+
+    fs::write(&temp_path, json_data)
+        .map_err(|e| AppError::io(Msg::ConfigWriteFailed.text(), &e))?;
+
+    fs::rename(&temp_path, &config_path)
+        .map_err(|e| AppError::io(Msg::ConfigWriteFailed.text(), &e))?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Поля конфигурации сервера, которые хранятся в зашифрованном виде.
+fn secret_fields_mut(server: &mut ServerConfig) -> Vec<&mut String> {
+    let mut fields = vec![&mut server.password];
+
+    if let AuthMethod::PublicKey { passphrase: Some(passphrase), .. } = &mut server.auth_method {
+        fields.push(passphrase);
+    }
+
+    if let SudoMode::Password { password } = &mut server.sudo {
+        fields.push(password);
+    }
+
+    fields
+}
+
+fn has_encrypted_secrets(servers: &mut [ServerConfig]) -> bool {
+    servers
+        .iter_mut()
+        .any(|server| secret_fields_mut(server).iter().any(|field| is_encrypted(field)))
+}
+
+/// Есть ли в servers.json зашифрованные секреты.
+pub fn servers_have_encrypted_secrets() -> Result<bool, AppError> {
+    Ok(has_encrypted_secrets(&mut read_servers_file()?))
+}
+
+fn load_servers_from_file(vault: &Vault) -> Result<Vec<ServerConfig>, AppError> {
+    let mut servers = read_servers_file()?;
+
+    if !has_encrypted_secrets(&mut servers) {
+        return Ok(servers);
+    }
+
+    vault.with_key(|key| {
+        for server in servers.iter_mut() {
+            for field in secret_fields_mut(server) {
+                *field = key.decrypt(field)?;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(servers)
+}
+
+fn save_servers_to_file(vault: &Vault, servers: &[ServerConfig]) -> Result<(), AppError> {
+    // Пока хранилище не создано, конфигурация пишется как раньше; после первой разблокировки — только зашифрованной
+    if !vault_initialized()? {
+        return write_servers_file(servers);
+    }
+
+    let mut servers = servers.to_vec();
+
+    vault.with_key(|key| {
+        for server in servers.iter_mut() {
+            for field in secret_fields_mut(server) {
+                *field = key.encrypt(field)?;
+            }
+        }
+        Ok(())
+    })?;
+
+    write_servers_file(&servers)
+}
+
+/// Перешифровывает секреты в servers.json: `decrypt` получает хранимое значение,
+/// `encrypt` — открытый текст. Возвращает число серверов, у которых изменились данные.
+pub fn reencrypt_servers<D, E>(decrypt: D, encrypt: E) -> Result<usize, AppError>
+where
+    D: Fn(&str) -> Result<String, AppError>,
+    E: Fn(&str) -> Result<String, AppError>,
+{
+    let (servers, changed) = reencrypted_servers(decrypt, encrypt)?;
+
+    if changed > 0 {
+        write_servers_file(&servers)?;
+    }
+
+    Ok(changed)
+}
+
+/// Перешифровывает секреты servers.json и затем вызывает `commit`, который сохраняет новый ключ.
+/// Пока `commit` не выполнен, рядом лежит копия прежнего servers.json: при ошибке она
+/// возвращается на место, а после сбоя процесса ее подхватывает `recover_servers_backup`.
+/// Возвращает число серверов, у которых изменились данные.
+pub fn reencrypt_servers_with_commit<D, E, C>(decrypt: D, encrypt: E, commit: C) -> Result<usize, AppError>
+where
+    D: Fn(&str) -> Result<String, AppError>,
+    E: Fn(&str) -> Result<String, AppError>,
+    C: FnOnce() -> Result<(), AppError>,
+{
+    let (servers, changed) = reencrypted_servers(decrypt, encrypt)?;
+
+    let config_path = ensure_config_file_exists()?;
+    let backup_path = get_servers_backup_path()?;
+
+    fs::copy(&config_path, &backup_path)
+        .map_err(|e| AppError::io(Msg::ConfigBackupFailed.text(), &e))?;
+
+    if let Err(error) = write_servers_file(&servers).and_then(|_| commit()) {
+        let _ = fs::rename(&backup_path, &config_path);
+        return Err(error);
+    }
+
+    let _ = fs::remove_file(&backup_path);
+    Ok(changed)
+}
+
+/// Разбирает копию servers.json, оставшуюся после прерванной смены мастер-пароля.
+/// `key_matches` проверяет, что секрет расшифровывается текущим ключом: если ключ
+/// не подходит к servers.json, но подходит к копии, копия возвращается на место.
+pub fn recover_servers_backup(key_matches: impl Fn(&str) -> bool) -> Result<(), AppError> {
+    let backup_path = get_servers_backup_path()?;
+
+    if !backup_path.exists() {
+        return Ok(());
+    }
+
+    let readable = |servers: &mut [ServerConfig]| {
+        servers.iter_mut().all(|server| {
+            secret_fields_mut(server).iter().all(|field| !is_encrypted(field) || key_matches(field))
+        })
+    };
+
+    let backup_readable = fs::read_to_string(&backup_path)
+        .ok()
+        .and_then(|json_data| serde_json::from_str::<Vec<ServerConfig>>(&json_data).ok())
+        .is_some_and(|mut backup| readable(&mut backup));
+
+    if backup_readable && !readable(&mut read_servers_file()?) {
+        fs::rename(&backup_path, get_config_file_path()?)
+            .map_err(|e| AppError::io(Msg::ConfigRestoreFailed.text(), &e))?;
+    } else {
+        let _ = fs::remove_file(&backup_path);
+    }
+
+    Ok(())
+}
+
+fn reencrypted_servers<D, E>(decrypt: D, encrypt: E) -> Result<(Vec<ServerConfig>, usize), AppError>
+where
+    D: Fn(&str) -> Result<String, AppError>,
+    E: Fn(&str) -> Result<String, AppError>,
+{
+    let mut servers = read_servers_file()?;
+    let mut changed = 0;
+
+    for server in servers.iter_mut() {
+        let mut server_changed = false;
+
+        for field in secret_fields_mut(server) {
+            let reencrypted = encrypt(&decrypt(field)?)?;
+            if reencrypted != *field {
+                *field = reencrypted;
+                server_changed = true;
+            }
+        }
+
+        if server_changed {
+            changed += 1;
+        }
+    }
+
+    Ok((servers, changed))
+}
+
+pub fn load_app_settings() -> Result<AppSettings, AppError> {
+    let settings_path = get_settings_file_path()?;
+
+    if !settings_path.exists() {
+        return Ok(AppSettings::default());
+    }
+
+    let json_data = fs::read_to_string(&settings_path)
+        .map_err(|e| AppError::io(Msg::SettingsReadFailed.text(), &e))?;
+
+    if json_data.trim().is_empty() {
+        return Ok(AppSettings::default());
+    }
+
+    serde_json::from_str(&json_data)
+        .map_err(|e| AppError::other(Msg::SettingsParseFailed.text(), e))
+}
+
+fn save_app_settings(settings: &AppSettings) -> Result<(), AppError> {
+    let settings_path = get_settings_file_path()?;
+
+    let json_data = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::other(Msg::SettingsSerializeFailed.text(), e))?;
+
+    fs::write(&settings_path, json_data)
+        .map_err(|e| AppError::io(Msg::SettingsWriteFailed.text(), &e))?;
+
+    Ok(())
+}
+
+#[command]
+pub fn load_settings() -> Result<AppSettings, AppError> {
+    load_app_settings()
+}
+
+#[command]
+pub fn save_settings(settings: AppSettings) -> Result<AppSettings, AppError> {
+    if settings.transfer_concurrency == 0 {
+        return Err(AppError::invalid_input(Msg::InvalidTransferConcurrency.text()));
+    }
+
+    settings.path_policy.validate()?;
+
+    save_app_settings(&settings)?;
+    set_language(settings.language);
+    Ok(settings)
+}
@@ -0,0 +1,260 @@
+use serde::Serialize;
+use ssh2::{Channel, Session};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::connection::{close_dedicated_session, get_dedicated_session, SshConnectionInfo};
+use crate::session_pool::{SessionLease, SessionPool};
+
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const DEFAULT_TERM: &str = "xterm-256color";
+
+enum TerminalInput {
+    Data(Vec<u8>),
+    Resize { cols: u32, rows: u32 },
+    Close,
+}
+
+#[derive(Clone, Serialize)]
+struct TerminalOutput {
+    terminal_id: String,
+    data: String,
+}
+
+#[derive(Clone, Serialize)]
+struct TerminalExit {
+    terminal_id: String,
+    exit_status: Option<i32>,
+}
+
+/// Открытые терминалы: у каждого свой поток, которому команды передаются через канал.
+#[derive(Default)]
+pub struct TerminalManager {
+    terminals: Mutex<HashMap<String, Sender<TerminalInput>>>,
+}
+
+impl TerminalManager {
+    fn send(&self, terminal_id: &str, input: TerminalInput) -> Result<(), AppError> {
+        let terminals = self.lock();
+        let sender = terminals.get(terminal_id)
+            .ok_or_else(|| AppError::not_found(Msg::TerminalNotFound.format(&[&terminal_id])))?;
+
+        sender.send(input)
+            .map_err(|_| AppError::not_found(Msg::TerminalAlreadyClosed.format(&[&terminal_id])))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Sender<TerminalInput>>> {
+        self.terminals.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn output_event(terminal_id: &str) -> String {
+    format!("terminal-output-{}", terminal_id)
+}
+
+fn exit_event(terminal_id: &str) -> String {
+    format!("terminal-exit-{}", terminal_id)
+}
+
+/// Открывает интерактивный shell с PTY. Вывод приходит событиями `terminal-output-<id>`,
+/// завершение — событием `terminal-exit-<id>`. Id задает фронтенд, чтобы подписаться на
+/// события до открытия и не потерять первый вывод shell.
+///
+/// Терминал берет из пула отдельную сессию: неблокирующий режим libssh2 действует на всю
+/// сессию, а блокирующее чтение PTY остановило бы остальные команды на общей сессии сервера.
+#[allow(clippy::too_many_arguments)]
+#[command]
+pub fn open_terminal(
+    app: AppHandle,
+    pool: State<'_, SessionPool>,
+    terminals: State<'_, TerminalManager>,
+    connection_info: SshConnectionInfo,
+    terminal_id: String,
+    cols: u32,
+    rows: u32,
+    term: Option<String>,
+) -> Result<(), AppError> {
+    let (sender, receiver) = mpsc::channel();
+
+    // Id занимается сразу: от него зависит и ключ сессии терминала в пуле
+    {
+        let mut terminals = terminals.lock();
+        if terminals.contains_key(&terminal_id) {
+            return Err(AppError::conflict(Msg::TerminalIdInUse.format(&[&terminal_id])));
+        }
+        terminals.insert(terminal_id.clone(), sender);
+    }
+
+    let (sess, mut channel) = match open_shell(&pool, &connection_info, &terminal_id, cols, rows, term.as_deref()) {
+        Ok(opened) => opened,
+        Err(error) => {
+            terminals.lock().remove(&terminal_id);
+            close_dedicated_session(&pool, &connection_info, &terminal_id, Msg::TerminalClosed.text());
+            return Err(error);
+        }
+    };
+
+    thread::spawn(move || {
+        let exit_status = run_terminal(&app, &terminal_id, &sess, &mut channel, receiver);
+
+        drop(channel);
+        drop(sess);
+        close_dedicated_session(&app.state::<SessionPool>(), &connection_info, &terminal_id, Msg::TerminalClosed.text());
+
+        app.state::<TerminalManager>().lock().remove(&terminal_id);
+
+        let _ = app.emit(
+            &exit_event(&terminal_id),
+            TerminalExit {
+                terminal_id: terminal_id.clone(),
+                exit_status,
+            },
+        );
+    });
+
+    Ok(())
+}
+
+fn open_shell(
+    pool: &SessionPool,
+    connection_info: &SshConnectionInfo,
+    terminal_id: &str,
+    cols: u32,
+    rows: u32,
+    term: Option<&str>,
+) -> Result<(SessionLease, Channel), AppError> {
+    let sess = get_dedicated_session(pool, connection_info, terminal_id)?;
+
+    let mut channel = sess.channel_session()
+        .map_err(|e| AppError::ssh(Msg::ChannelOpenFailed.text(), &e))?;
+
+    channel.request_pty(term.unwrap_or(DEFAULT_TERM), None, Some((cols, rows, 0, 0)))
+        .map_err(|e| AppError::ssh(Msg::PtyRequestFailed.text(), &e))?;
+
+    channel.shell()
+        .map_err(|e| AppError::ssh(Msg::ShellStartFailed.text(), &e))?;
+
+    Ok((sess, channel))
+}
+
+#[command]
+pub fn write_terminal(terminals: State<'_, TerminalManager>, terminal_id: String, data: String) -> Result<(), AppError> {
+    terminals.send(&terminal_id, TerminalInput::Data(data.into_bytes()))
+}
+
+#[command]
+pub fn resize_terminal(terminals: State<'_, TerminalManager>, terminal_id: String, cols: u32, rows: u32) -> Result<(), AppError> {
+    terminals.send(&terminal_id, TerminalInput::Resize { cols, rows })
+}
+
+#[command]
+pub fn close_terminal(terminals: State<'_, TerminalManager>, terminal_id: String) -> Result<(), AppError> {
+    terminals.send(&terminal_id, TerminalInput::Close)
+}
+
+/// Цикл терминала: передает ввод в канал и пересылает вывод фронтенду, пока shell не завершится
+/// или терминал не закроют. Возвращает код завершения shell, если он известен.
+fn run_terminal(
+    app: &AppHandle,
+    terminal_id: &str,
+    sess: &Session,
+    channel: &mut Channel,
+    receiver: Receiver<TerminalInput>,
+) -> Option<i32> {
+    sess.set_blocking(false);
+
+    let event = output_event(terminal_id);
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    // Хвост неполного UTF-8 символа, разрезанного между двумя чтениями
+    let mut pending: Vec<u8> = Vec::new();
+
+    'session: loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(TerminalInput::Data(data)) => {
+                    if write_all(channel, &data).is_err() {
+                        break 'session;
+                    }
+                }
+                Ok(TerminalInput::Resize { cols, rows }) => {
+                    let _ = retry_would_block(|| channel.request_pty_size(cols, rows, None, None).map_err(io::Error::from));
+                }
+                Ok(TerminalInput::Close) | Err(TryRecvError::Disconnected) => break 'session,
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        match channel.read(&mut buffer) {
+            Ok(0) => {
+                if channel.eof() {
+                    break;
+                }
+                thread::sleep(IDLE_POLL_INTERVAL);
+            }
+            Ok(read) => {
+                pending.extend_from_slice(&buffer[..read]);
+                let data = take_complete_utf8(&mut pending);
+
+                if !data.is_empty() {
+                    let _ = app.emit(
+                        &event,
+                        TerminalOutput {
+                            terminal_id: terminal_id.to_string(),
+                            data,
+                        },
+                    );
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(IDLE_POLL_INTERVAL),
+            Err(_) => break,
+        }
+    }
+
+    sess.set_blocking(true);
+    let _ = channel.close();
+    let _ = channel.wait_close();
+
+    channel.exit_status().ok()
+}
+
+fn write_all(channel: &mut Channel, mut data: &[u8]) -> io::Result<()> {
+    while !data.is_empty() {
+        let written = retry_would_block(|| channel.write(data))?;
+        data = &data[written..];
+    }
+
+    retry_would_block(|| channel.flush())
+}
+
+fn retry_would_block<T>(mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match operation() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(IDLE_POLL_INTERVAL),
+            result => return result,
+        }
+    }
+}
+
+/// Забирает из буфера корректный UTF-8 префикс, оставляя незавершенный символ до следующего чтения.
+/// Невалидные байты заменяются на U+FFFD, чтобы бинарный вывод не останавливал терминал.
+pub fn take_complete_utf8(pending: &mut Vec<u8>) -> String {
+    let complete_len = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+
+    let rest = pending.split_off(complete_len);
+    let data = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+
+    data
+}
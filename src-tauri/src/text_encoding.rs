@@ -0,0 +1,217 @@
+use encoding_rs::{Encoding, KOI8_R, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+
+/// Сколько байт от начала файла используется, чтобы выбрать между однобайтовыми кодировками.
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Перевод строки, которым записан файл.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+/// Кодировка, BOM и переводы строк текстового файла. Редактор получает текст
+/// в UTF-8 с `\n`, а при сохранении он переводится обратно в этот формат.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextFormat {
+    /// Имя кодировки: `UTF-8`, `UTF-16LE`, `UTF-16BE`, `windows-1251`, `KOI8-R`, `windows-1252`
+    pub encoding: String,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat {
+            encoding: UTF_8.name().to_string(),
+            bom: false,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+impl TextFormat {
+    /// Определяет формат по содержимому: BOM, затем корректность UTF-8, затем
+    /// выбор между CP1251 и KOI8-R по доле строчных русских букв.
+    pub fn detect(bytes: &[u8]) -> Self {
+        let (encoding, bom) = match Encoding::for_bom(bytes) {
+            Some((encoding, _)) => (encoding, true),
+            None => (detect_without_bom(bytes), false),
+        };
+
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[..bytes.len().min(SAMPLE_SIZE)]);
+
+        TextFormat {
+            encoding: encoding.name().to_string(),
+            bom,
+            line_ending: detect_line_ending(&text),
+        }
+    }
+
+    fn encoding(&self) -> Result<&'static Encoding, AppError> {
+        Encoding::for_label(self.encoding.as_bytes())
+            .ok_or_else(|| AppError::invalid_input(Msg::UnknownEncoding.format(&[&self.encoding])))
+    }
+
+    /// Текст для редактора: без BOM и с переводами строк `\n`.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, AppError> {
+        let encoding = self.encoding()?;
+        let bytes = match Encoding::for_bom(bytes) {
+            Some((bom_encoding, bom_length)) if bom_encoding == encoding => &bytes[bom_length..],
+            _ => bytes,
+        };
+
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        if had_errors {
+            return Err(AppError::new(ErrorCode::Unsupported, Msg::InvalidBytesForEncoding.format(&[&encoding.name()])));
+        }
+
+        Ok(text.replace("\r\n", "\n").replace('\r', "\n"))
+    }
+
+    /// Содержимое файла в исходной кодировке, с BOM и переводами строк этого формата.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, AppError> {
+        let encoding = self.encoding()?;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let text = match self.line_ending {
+            LineEnding::Lf => text,
+            line_ending => text.replace('\n', line_ending.as_str()),
+        };
+
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+
+        // encoding_rs не кодирует в UTF-16, поэтому эти кодировки собираются вручную
+        if encoding == UTF_16LE || encoding == UTF_16BE {
+            let big_endian = encoding == UTF_16BE;
+            let units = self.bom.then_some('\u{feff}' as u16).into_iter().chain(text.encode_utf16());
+
+            for unit in units {
+                bytes.extend_from_slice(&if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() });
+            }
+
+            return Ok(bytes);
+        }
+
+        if self.bom && encoding == UTF_8 {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+
+        let (encoded, _, had_errors) = encoding.encode(&text);
+        if had_errors {
+            let unmappable = text.chars()
+                .find(|c| encoding.encode(c.encode_utf8(&mut [0; 4])).2)
+                .unwrap_or('?');
+            return Err(AppError::invalid_input(Msg::UnmappableCharacter.format(&[&unmappable, &encoding.name()])));
+        }
+
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+fn detect_without_bom(bytes: &[u8]) -> &'static Encoding {
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => return UTF_8,
+        // Выборка могла разрезать многобайтовый символ в конце
+        Err(e) if e.error_len().is_none() && sample.len() < bytes.len() => return UTF_8,
+        Err(_) => {}
+    }
+
+    // Строчные буквы в CP1251 — это заглавные в KOI8-R и наоборот, а в обычном
+    // тексте строчных больше. Если русских букв нет совсем, это скорее латиница.
+    let cp1251 = lowercase_cyrillic(WINDOWS_1251, sample);
+    let koi8 = lowercase_cyrillic(KOI8_R, sample);
+
+    match (cp1251, koi8) {
+        (0, 0) => WINDOWS_1252,
+        (cp1251, koi8) if koi8 > cp1251 => KOI8_R,
+        _ => WINDOWS_1251,
+    }
+}
+
+fn lowercase_cyrillic(encoding: &'static Encoding, bytes: &[u8]) -> usize {
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    text.chars().filter(|c| matches!(c, 'а'..='я' | 'ё')).count()
+}
+
+/// Преобладающий перевод строки; файл без переводов строк считается LF.
+fn detect_line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let cr = text.matches('\r').count() - crlf;
+    let lf = text.matches('\n').count() - crlf;
+
+    if crlf > lf && crlf >= cr {
+        LineEnding::Crlf
+    } else if cr > lf && cr > crlf {
+        LineEnding::Cr
+    } else {
+        LineEnding::Lf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_utf8_and_bom() {
+        let format = TextFormat::detect("Привет\n".as_bytes());
+        assert_eq!(format, TextFormat::default());
+
+        let format = TextFormat::detect(b"\xEF\xBB\xBFhello\r\n");
+        assert_eq!(format.encoding, "UTF-8");
+        assert!(format.bom);
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert_eq!(format.decode(b"\xEF\xBB\xBFhello\r\n").unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn tells_cp1251_from_koi8() {
+        let text = "# настройки сервера\nпорт = 8080\n";
+        let (cp1251, _, _) = WINDOWS_1251.encode(text);
+        let (koi8, _, _) = KOI8_R.encode(text);
+
+        assert_eq!(TextFormat::detect(&cp1251).encoding, "windows-1251");
+        assert_eq!(TextFormat::detect(&koi8).encoding, "KOI8-R");
+    }
+
+    #[test]
+    fn round_trips_original_format() {
+        let original = KOI8_R.encode("строка один\r\nстрока два\r\n").0.into_owned();
+        let format = TextFormat::detect(&original);
+
+        let text = format.decode(&original).unwrap();
+        assert_eq!(text, "строка один\nстрока два\n");
+        assert_eq!(format.encode(&text).unwrap(), original);
+    }
+
+    #[test]
+    fn rejects_unmappable_characters() {
+        let format = TextFormat {
+            encoding: "windows-1251".to_string(),
+            bom: false,
+            line_ending: LineEnding::Lf,
+        };
+
+        assert!(format.encode("日本").unwrap_err().message.contains('日'));
+    }
+}
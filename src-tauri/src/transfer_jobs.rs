@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+use crate::connect_copy::{run_server_transfer, FileTransferRequest, TransferObserver, TransferStats, TransferSummary};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::local_transfer::{run_download, run_upload, LocalTransferRequest};
+use crate::session_pool::SessionPool;
+use crate::storage::{load_app_settings, DEFAULT_TRANSFER_CONCURRENCY};
+
+const PROGRESS_EVENT: &str = "transfer-progress";
+const FINISHED_EVENT: &str = "transfer-finished";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Что передавать: между серверами, с компьютера на сервер или с сервера на компьютер.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransferJobRequest {
+    BetweenServers(FileTransferRequest),
+    Upload(LocalTransferRequest),
+    Download(LocalTransferRequest),
+}
+
+impl TransferJobRequest {
+    fn source(&self) -> &str {
+        match self {
+            TransferJobRequest::BetweenServers(request) => &request.file_path,
+            TransferJobRequest::Upload(request) => &request.local_path,
+            TransferJobRequest::Download(request) => &request.remote_path,
+        }
+    }
+
+    fn destination(&self) -> &str {
+        match self {
+            TransferJobRequest::BetweenServers(request) => &request.destination_path,
+            TransferJobRequest::Upload(request) => &request.remote_path,
+            TransferJobRequest::Download(request) => &request.local_path,
+        }
+    }
+
+    fn run(&self, pool: &SessionPool, summary: &mut TransferSummary) -> Result<String, AppError> {
+        match self {
+            TransferJobRequest::BetweenServers(request) => run_server_transfer(pool, request, summary),
+            TransferJobRequest::Upload(request) => run_upload(pool, request, summary),
+            TransferJobRequest::Download(request) => run_download(pool, request, summary),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferJobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TransferJobState {
+    fn is_finished(self) -> bool {
+        matches!(self, TransferJobState::Completed | TransferJobState::Failed | TransferJobState::Cancelled)
+    }
+}
+
+/// Состояние задачи передачи. Это же значение приходит в событиях
+/// `transfer-progress` (во время работы) и `transfer-finished` (по завершении).
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferJobInfo {
+    pub job_id: String,
+    pub source: String,
+    pub destination: String,
+    pub state: TransferJobState,
+    /// Итоговое сообщение или текст ошибки
+    pub message: Option<String>,
+    /// Ошибка неудачной передачи с кодом и подробностями
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppError>,
+    pub bytes_transferred: u64,
+    pub files_copied: usize,
+    pub files_skipped: usize,
+    pub current_file: Option<String>,
+    /// Средняя скорость с начала передачи, байт в секунду
+    pub rate: f64,
+}
+
+struct TransferJob {
+    info: TransferJobInfo,
+    request: Option<TransferJobRequest>,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct TransferQueue {
+    jobs: HashMap<String, TransferJob>,
+    pending: VecDeque<String>,
+    running: usize,
+}
+
+/// Очередь фоновых передач. Одновременно выполняется не больше
+/// `AppSettings::transfer_concurrency` задач, остальные ждут своей очереди.
+#[derive(Default)]
+pub struct TransferManager {
+    queue: Mutex<TransferQueue>,
+    next_id: AtomicU64,
+}
+
+impl TransferManager {
+    fn lock(&self) -> std::sync::MutexGuard<'_, TransferQueue> {
+        self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn update_progress(&self, job_id: &str, stats: &TransferStats, rate: f64) -> Option<TransferJobInfo> {
+        let mut queue = self.lock();
+        let job = queue.jobs.get_mut(job_id)?;
+
+        job.info.bytes_transferred = stats.bytes;
+        job.info.files_copied = stats.copied;
+        job.info.files_skipped = stats.skipped;
+        job.info.current_file = Some(stats.current_file.to_string());
+        job.info.rate = rate;
+
+        Some(job.info.clone())
+    }
+}
+
+/// Передает ход задачи фронтенду не чаще `PROGRESS_INTERVAL` и прерывает ее после отмены.
+struct JobObserver {
+    app: AppHandle,
+    job_id: String,
+    cancelled: Arc<AtomicBool>,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl TransferObserver for JobObserver {
+    fn progress(&mut self, stats: &TransferStats) -> Result<(), AppError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(AppError::new(ErrorCode::Cancelled, Msg::TransferCancelled.text()));
+        }
+
+        let now = Instant::now();
+        if self.last_emit.is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL) {
+            return Ok(());
+        }
+        self.last_emit = Some(now);
+
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let rate = if elapsed > 0.0 { stats.bytes as f64 / elapsed } else { 0.0 };
+
+        if let Some(info) = self.app.state::<TransferManager>().update_progress(&self.job_id, stats, rate) {
+            let _ = self.app.emit(PROGRESS_EVENT, info);
+        }
+
+        Ok(())
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+fn transfer_concurrency() -> usize {
+    load_app_settings()
+        .map(|settings| settings.transfer_concurrency)
+        .unwrap_or(DEFAULT_TRANSFER_CONCURRENCY)
+        .max(1)
+}
+
+/// Запускает задачи из очереди, пока не достигнут предел одновременных передач.
+fn schedule(app: &AppHandle) {
+    let manager = app.state::<TransferManager>();
+    let concurrency = transfer_concurrency();
+    let mut queue = manager.lock();
+
+    while queue.running < concurrency {
+        let Some(job_id) = queue.pending.pop_front() else {
+            break;
+        };
+
+        let Some(job) = queue.jobs.get_mut(&job_id) else {
+            continue;
+        };
+
+        let Some(request) = job.request.take() else {
+            continue;
+        };
+
+        job.info.state = TransferJobState::Running;
+        let cancelled = job.cancelled.clone();
+        let info = job.info.clone();
+        queue.running += 1;
+
+        let _ = app.emit(PROGRESS_EVENT, info);
+
+        let app = app.clone();
+        thread::spawn(move || run_job(app, job_id, request, cancelled));
+    }
+}
+
+fn run_job(app: AppHandle, job_id: String, request: TransferJobRequest, cancelled: Arc<AtomicBool>) {
+    let started = Instant::now();
+    let observer = JobObserver {
+        app: app.clone(),
+        job_id: job_id.clone(),
+        cancelled: cancelled.clone(),
+        started,
+        last_emit: None,
+    };
+
+    let mut summary = TransferSummary::observed(Box::new(observer));
+    // Паника в передаче не должна оставить занятым место в очереди
+    let result = panic::catch_unwind(AssertUnwindSafe(|| request.run(&app.state::<SessionPool>(), &mut summary)))
+        .unwrap_or_else(|_| Err(AppError::internal(Msg::TransferCrashed.text())));
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let finished = {
+        let manager = app.state::<TransferManager>();
+        let mut queue = manager.lock();
+        queue.running -= 1;
+
+        queue.jobs.get_mut(&job_id).map(|job| {
+            let (state, message, error) = match result {
+                Ok(message) => (TransferJobState::Completed, message, None),
+                Err(_) if cancelled.load(Ordering::Relaxed) => (TransferJobState::Cancelled, Msg::TransferCancelled.text().to_string(), None),
+                Err(error) => (TransferJobState::Failed, error.message.clone(), Some(error)),
+            };
+
+            job.info.state = state;
+            job.info.message = Some(message);
+            job.info.error = error;
+            job.info.bytes_transferred = summary.bytes;
+            job.info.files_copied = summary.copied;
+            job.info.files_skipped = summary.skipped;
+            job.info.current_file = None;
+            job.info.rate = if elapsed > 0.0 { summary.bytes as f64 / elapsed } else { 0.0 };
+            job.info.clone()
+        })
+    };
+
+    if let Some(info) = finished {
+        let _ = app.emit(FINISHED_EVENT, info);
+    }
+
+    schedule(&app);
+}
+
+/// Ставит передачу в очередь и сразу возвращает id задачи. Ход передачи приходит
+/// событиями `transfer-progress`, результат — событием `transfer-finished`.
+#[command]
+pub fn start_transfer(app: AppHandle, transfers: State<'_, TransferManager>, transfer_request: TransferJobRequest) -> Result<String, AppError> {
+    let job_id = format!("transfer-{}", transfers.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+
+    let info = TransferJobInfo {
+        job_id: job_id.clone(),
+        source: transfer_request.source().to_string(),
+        destination: transfer_request.destination().to_string(),
+        state: TransferJobState::Queued,
+        message: None,
+        error: None,
+        bytes_transferred: 0,
+        files_copied: 0,
+        files_skipped: 0,
+        current_file: None,
+        rate: 0.0,
+    };
+
+    {
+        let mut queue = transfers.lock();
+        queue.jobs.insert(
+            job_id.clone(),
+            TransferJob {
+                info: info.clone(),
+                request: Some(transfer_request),
+                cancelled: Arc::new(AtomicBool::new(false)),
+            },
+        );
+        queue.pending.push_back(job_id.clone());
+    }
+
+    let _ = app.emit(PROGRESS_EVENT, info);
+    schedule(&app);
+
+    Ok(job_id)
+}
+
+/// Отменяет задачу: ожидающая убирается из очереди, выполняющаяся прерывается на ближайшем блоке данных.
+#[command]
+pub fn cancel_transfer(app: AppHandle, transfers: State<'_, TransferManager>, job_id: String) -> Result<String, AppError> {
+    let mut queue = transfers.lock();
+
+    let job = queue.jobs.get_mut(&job_id)
+        .ok_or_else(|| AppError::not_found(Msg::JobNotFound.format(&[&job_id])))?;
+
+    match job.info.state {
+        TransferJobState::Queued => {
+            job.request = None;
+            job.info.state = TransferJobState::Cancelled;
+            job.info.message = Some(Msg::TransferCancelled.text().to_string());
+            let info = job.info.clone();
+
+            queue.pending.retain(|id| id != &job_id);
+            let _ = app.emit(FINISHED_EVENT, info);
+
+            Ok(Msg::TransferDequeued.text().to_string())
+        }
+        TransferJobState::Running => {
+            job.cancelled.store(true, Ordering::Relaxed);
+            Ok(Msg::TransferStopping.text().to_string())
+        }
+        _ => Err(AppError::conflict(Msg::JobFinished.format(&[&job_id]))),
+    }
+}
+
+#[command]
+pub fn list_transfers(transfers: State<'_, TransferManager>) -> Result<Vec<TransferJobInfo>, AppError> {
+    let mut jobs: Vec<TransferJobInfo> = transfers.lock().jobs.values().map(|job| job.info.clone()).collect();
+    jobs.sort_by_key(|job| job.job_id.trim_start_matches("transfer-").parse::<u64>().unwrap_or(0));
+    Ok(jobs)
+}
+
+/// Убирает из списка завершенные, отмененные и неудачные задачи.
+#[command]
+pub fn clear_finished_transfers(transfers: State<'_, TransferManager>) -> Result<usize, AppError> {
+    let mut queue = transfers.lock();
+    let before = queue.jobs.len();
+    queue.jobs.retain(|_, job| !job.info.state.is_finished());
+    Ok(before - queue.jobs.len())
+}
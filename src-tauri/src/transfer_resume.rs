@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connect_copy::{copy_with_progress, TransferSummary};
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::storage::get_config_dir;
+
+/// Сколько байт перед точкой продолжения сравнивается у источника и частичного файла.
+const VERIFY_WINDOW: u64 = 1024 * 1024;
+
+/// Как часто во время копирования сохранять в журнал, сколько уже записано.
+const JOURNAL_INTERVAL: u64 = 8 * 1024 * 1024;
+
+/// Записи старше этого срока считаются брошенными и не используются.
+const MAX_ENTRY_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+// Журнал читают и пишут параллельные задачи передачи
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Недокачанный файл: откуда и куда шла передача, версия источника и сколько уже записано.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartialTransfer {
+    pub source: String,
+    pub destination: String,
+    pub source_size: Option<u64>,
+    pub source_mtime: Option<u64>,
+    pub bytes_written: u64,
+    pub updated_at: u64,
+}
+
+/// Версия источника: если размер или время изменения поменялись, частичный файл устарел.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceVersion {
+    pub size: Option<u64>,
+    pub mtime: Option<u64>,
+}
+
+/// Частичный файл, который можно обрезать до точки продолжения.
+pub trait PartialFile: Read + Write + Seek {
+    fn truncate(&mut self, len: u64) -> io::Result<()>;
+}
+
+impl PartialFile for fs::File {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.set_len(len)
+    }
+}
+
+impl PartialFile for ssh2::File {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.setstat(ssh2::FileStat {
+            size: Some(len),
+            uid: None,
+            gid: None,
+            perm: None,
+            atime: None,
+            mtime: None,
+        })
+        .map_err(io::Error::from)
+    }
+}
+
+/// Передаваемый файл: источник, назначение и версия источника. Ключ журнала строится
+/// по источнику и назначению, поэтому повторный запуск находит свой частичный файл.
+pub struct ResumableFile {
+    pub key: String,
+    pub source: String,
+    pub destination: String,
+    pub version: SourceVersion,
+}
+
+impl ResumableFile {
+    pub fn new(source: String, destination: String, version: SourceVersion) -> Self {
+        ResumableFile {
+            key: resume_key(&source, &destination),
+            source,
+            destination,
+            version,
+        }
+    }
+}
+
+fn get_journal_path() -> Result<PathBuf, AppError> {
+    let mut config_dir = get_config_dir()?;
+    config_dir.push("partial_transfers.json");
+    Ok(config_dir)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn read_journal() -> HashMap<String, PartialTransfer> {
+    // Поврежденный журнал не должен ломать передачу: в худшем случае она начнется заново
+    get_journal_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_journal(journal: &HashMap<String, PartialTransfer>) -> Result<(), AppError> {
+    let journal_path = get_journal_path()?;
+
+    let json_data = serde_json::to_string_pretty(journal)
+        .map_err(|e| AppError::other(Msg::JournalSerializeFailed.text(), e))?;
+
+    fs::write(&journal_path, json_data)
+        .map_err(|e| AppError::io(Msg::JournalWriteFailed.text(), &e))
+}
+
+fn update_journal(update: impl FnOnce(&mut HashMap<String, PartialTransfer>)) {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut journal = read_journal();
+    let now = now_secs();
+    journal.retain(|_, entry| now.saturating_sub(entry.updated_at) < MAX_ENTRY_AGE_SECS);
+    update(&mut journal);
+
+    let _ = write_journal(&journal);
+}
+
+fn resume_key(source: &str, destination: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    destination.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// С какого байта продолжать: записанное в журнале, но не больше фактического размера
+/// частичного файла. Если источник изменился или записи нет, передача начинается с нуля.
+fn resume_offset(file: &ResumableFile, partial_size: Option<u64>) -> u64 {
+    let Some(partial_size) = partial_size else {
+        return 0;
+    };
+
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match read_journal().get(&file.key) {
+        Some(entry) if entry.source_size == file.version.size && entry.source_mtime == file.version.mtime => {
+            entry.bytes_written.min(partial_size)
+        }
+        _ => 0,
+    }
+}
+
+/// Запоминает, сколько байт уже записано в частичный файл.
+fn record_progress(file: &ResumableFile, bytes_written: u64) {
+    update_journal(|journal| {
+        journal.insert(
+            file.key.clone(),
+            PartialTransfer {
+                source: file.source.clone(),
+                destination: file.destination.clone(),
+                source_size: file.version.size,
+                source_mtime: file.version.mtime,
+                bytes_written,
+                updated_at: now_secs(),
+            },
+        );
+    });
+}
+
+/// Удаляет запись после успешного завершения или отмены передачи.
+pub fn forget(key: &str) {
+    update_journal(|journal| {
+        journal.remove(key);
+    });
+}
+
+/// Частичный файл, который по ходу записи отмечает в журнале точку продолжения,
+/// чтобы после падения приложения не передавать файл заново.
+struct JournaledWriter<'a, W> {
+    file: &'a ResumableFile,
+    inner: &'a mut W,
+    written: u64,
+    recorded: u64,
+}
+
+impl<W: Write> Write for JournaledWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.written += len as u64;
+
+        if self.written - self.recorded >= JOURNAL_INTERVAL {
+            record_progress(self.file, self.written);
+            self.recorded = self.written;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn read_window(file: &mut (impl Read + Seek), start: u64, len: usize) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut buffer = vec![0u8; len];
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+/// Сравнивает последние `VERIFY_WINDOW` байт перед `offset` у источника и частичного файла.
+/// Позиции чтения обоих файлов после вызова не определены.
+fn tail_matches(source: &mut (impl Read + Seek), partial: &mut (impl Read + Seek), offset: u64) -> bool {
+    let start = offset.saturating_sub(VERIFY_WINDOW);
+    let len = (offset - start) as usize;
+
+    match (read_window(source, start, len), read_window(partial, start, len)) {
+        (Some(source_window), Some(partial_window)) => source_window == partial_window,
+        _ => false,
+    }
+}
+
+/// Дописывает `partial` из `source` с места обрыва предыдущей попытки. Точка продолжения берется
+/// из журнала и подтверждается сравнением хвоста уже записанных данных; иначе копирование
+/// начинается заново. В журнал записывается, сколько успели передать: каждые `JOURNAL_INTERVAL`
+/// байт и при ошибке.
+/// После отмены запись удаляется, а сам частичный файл удаляет вызывающий код.
+pub fn copy_resumable(
+    file: &ResumableFile,
+    source: &mut (impl Read + Seek),
+    partial: &mut impl PartialFile,
+    partial_size: Option<u64>,
+    summary: &mut TransferSummary,
+) -> Result<(), AppError> {
+    let mut offset = resume_offset(file, partial_size);
+
+    if offset > 0 && !tail_matches(source, partial, offset) {
+        offset = 0;
+    }
+
+    partial.truncate(offset)
+        .and_then(|_| partial.seek(SeekFrom::Start(offset)))
+        .and_then(|_| source.seek(SeekFrom::Start(offset)))
+        .map_err(|e| AppError::io(Msg::PartialFilePrepareFailed.text(), &e))?;
+
+    record_progress(file, offset);
+
+    let mut writer = JournaledWriter {
+        file,
+        inner: partial,
+        written: offset,
+        recorded: offset,
+    };
+
+    let result = copy_with_progress(source, &mut writer, summary);
+
+    if result.is_err() && summary.is_cancelled() {
+        forget(&file.key);
+    } else {
+        // Запись нужна и после успеха: если упадет следующий шаг (перенос на место), данные не передаются заново
+        record_progress(file, writer.written);
+    }
+
+    result
+}
@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+use ssh2::{Session, Sftp};
+use std::cmp::Reverse;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, State};
+
+use crate::error::AppError;
+use crate::i18n::Msg;
+use crate::connection::{get_session, run_with_sudo_fallback, SshConnectionInfo};
+use crate::remote_command::RemoteCommand;
+use crate::session_pool::SessionPool;
+
+/// Удаленные файлы и папки лежат в `<корзина>/files/<id>`, описание — в `<корзина>/info/<id>.json`.
+const FILES_DIR: &str = "files";
+const INFO_DIR: &str = "info";
+
+/// Запись корзины: что удалено, откуда и когда.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub original_path: String,
+    /// Время удаления, секунды Unix
+    pub deleted_at: u64,
+    pub is_dir: bool,
+}
+
+/// Корзина сервера с уже раскрытым `~`.
+struct Trash {
+    root: String,
+}
+
+impl Trash {
+    /// Корзина из настроек сервера или `None`, если она не задана.
+    fn open(sftp: &Sftp, connection_info: &SshConnectionInfo) -> Result<Option<Trash>, AppError> {
+        let Some(dir) = connection_info.trash_dir.as_deref() else {
+            return Ok(None);
+        };
+
+        let root = match dir.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+                let home = sftp.realpath(Path::new("."))
+                    .map_err(|e| AppError::ssh(Msg::HomeDirResolveFailed.text(), &e))?;
+                format!("{}{}", home.to_string_lossy(), rest)
+            }
+            _ => dir.to_string(),
+        };
+
+        Ok(Some(Trash {
+            root: root.trim_end_matches('/').to_string(),
+        }))
+    }
+
+    fn required(sftp: &Sftp, connection_info: &SshConnectionInfo) -> Result<Trash, AppError> {
+        Trash::open(sftp, connection_info)?
+            .ok_or_else(|| AppError::invalid_input(Msg::TrashNotConfigured.text()))
+    }
+
+    fn files_path(&self, id: &str) -> String {
+        format!("{}/{}/{}", self.root, FILES_DIR, id)
+    }
+
+    fn info_path(&self, id: &str) -> String {
+        format!("{}/{}/{}.json", self.root, INFO_DIR, id)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        path == self.root || path.starts_with(&format!("{}/", self.root))
+    }
+
+    fn ensure_exists(&self, sftp: &Sftp) -> Result<(), AppError> {
+        for dir in [self.root.clone(), format!("{}/{}", self.root, FILES_DIR), format!("{}/{}", self.root, INFO_DIR)] {
+            if sftp.stat(Path::new(&dir)).is_err() {
+                sftp.mkdir(Path::new(&dir), 0o700)
+                    .map_err(|e| AppError::ssh(&Msg::TrashDirCreateFailed.format(&[&dir]), &e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_entry(&self, sftp: &Sftp, id: &str) -> Result<TrashEntry, AppError> {
+        let mut json = String::new();
+        sftp.open(Path::new(&self.info_path(id)))
+            .map_err(|e| AppError::ssh(&Msg::TrashEntryNotFound.format(&[&id]), &e))?
+            .read_to_string(&mut json)
+            .map_err(|e| AppError::io(&Msg::TrashEntryReadFailed.format(&[&id]), &e))?;
+
+        serde_json::from_str(&json)
+            .map_err(|e| AppError::other(&Msg::TrashEntryCorrupted.format(&[&id]), e))
+    }
+}
+
+/// ID записи: время удаления и имя файла, чтобы одноименные файлы не пересекались.
+fn entry_id(path: &str) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("item");
+    format!("{}-{}", nanos, name)
+}
+
+fn validate_id(id: &str) -> Result<(), AppError> {
+    if id.is_empty() || id.contains('/') || id == "." || id == ".." {
+        return Err(AppError::invalid_input(Msg::InvalidTrashId.format(&[&id])));
+    }
+
+    Ok(())
+}
+
+/// Перемещает путь в корзину сервера. Возвращает `None`, если корзина не настроена или путь
+/// уже в ней (тогда вызывающий удаляет его насовсем), иначе — понадобился ли sudo.
+pub fn move_to_trash(sess: &Session, connection_info: &SshConnectionInfo, path: &str, is_dir: bool) -> Result<Option<bool>, AppError> {
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let Some(trash) = Trash::open(&sftp, connection_info)? else {
+        return Ok(None);
+    };
+
+    if trash.contains(path.trim_end_matches('/')) {
+        return Ok(None);
+    }
+
+    trash.ensure_exists(&sftp)?;
+
+    let entry = TrashEntry {
+        id: entry_id(path),
+        original_path: path.to_string(),
+        deleted_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        is_dir,
+    };
+
+    let json = serde_json::to_string_pretty(&entry)
+        .map_err(|e| AppError::other(Msg::TrashEntrySerializeFailed.text(), e))?;
+
+    let info_path = trash.info_path(&entry.id);
+    sftp.create(Path::new(&info_path))
+        .map_err(|e| AppError::ssh(Msg::TrashWriteFailed.text(), &e))?
+        .write_all(json.as_bytes())
+        .map_err(|e| AppError::io(Msg::TrashWriteFailed.text(), &e))?;
+
+    let command = RemoteCommand::new("mv").arg("--").arg(path).arg(trash.files_path(&entry.id));
+
+    match run_with_sudo_fallback(sess, connection_info, &command, Msg::SudoTrashFailed.text()) {
+        Ok(elevated) => Ok(Some(elevated)),
+        Err(e) => {
+            let _ = sftp.unlink(Path::new(&info_path));
+            Err(e)
+        }
+    }
+}
+
+/// Содержимое корзины, сначала недавно удаленное.
+#[command]
+pub fn list_trash(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo) -> Result<Vec<TrashEntry>, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let trash = Trash::required(&sftp, &connection_info)?;
+
+    let Ok(items) = sftp.readdir(Path::new(&format!("{}/{}", trash.root, INFO_DIR))) else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries: Vec<TrashEntry> = items
+        .iter()
+        .filter_map(|(path, _)| path.file_name()?.to_str()?.strip_suffix(".json").map(str::to_string))
+        .filter_map(|id| trash.read_entry(&sftp, &id).ok())
+        .collect();
+
+    entries.sort_by_key(|entry| Reverse(entry.deleted_at));
+    Ok(entries)
+}
+
+/// Возвращает запись корзины на прежнее место. Если там уже что-то есть, восстановление отклоняется.
+#[command]
+pub fn restore_from_trash(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, id: String) -> Result<String, AppError> {
+    validate_id(&id)?;
+
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let trash = Trash::required(&sftp, &connection_info)?;
+    let entry = trash.read_entry(&sftp, &id)?;
+
+    if sftp.lstat(Path::new(&entry.original_path)).is_ok() {
+        return Err(AppError::conflict(Msg::RestoreTargetExists.format(&[&entry.original_path])));
+    }
+
+    let parent = match entry.original_path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", _)) | None => "/",
+        Some((parent, _)) => parent,
+    };
+
+    let command = RemoteCommand::new("mkdir").arg("-p").arg("--").arg(parent)
+        .and(RemoteCommand::new("mv").arg("--").arg(trash.files_path(&id)).arg(&entry.original_path));
+
+    let elevated = run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoRestoreFailed.text())?;
+
+    let _ = sftp.unlink(Path::new(&trash.info_path(&id)));
+
+    let restored = Msg::Restored.format(&[&entry.original_path]);
+
+    if elevated {
+        return Ok(Msg::AsAdministrator.format(&[&restored]));
+    }
+
+    Ok(restored)
+}
+
+/// Удаляет насовсем указанные записи корзины или, если `ids` не переданы, всю корзину.
+#[command]
+pub fn empty_trash(pool: State<'_, SessionPool>, connection_info: SshConnectionInfo, ids: Option<Vec<String>>) -> Result<String, AppError> {
+    let sess = get_session(&pool, &connection_info)?;
+
+    let sftp = sess.sftp()
+        .map_err(|e| AppError::ssh(Msg::SftpFailed.text(), &e))?;
+
+    let trash = Trash::required(&sftp, &connection_info)?;
+
+    let command = match &ids {
+        Some(ids) => {
+            let mut command = RemoteCommand::new("rm").arg("-rf").arg("--");
+            for id in ids {
+                validate_id(id)?;
+                command = command.arg(trash.files_path(id)).arg(trash.info_path(id));
+            }
+            command
+        }
+        None => RemoteCommand::new("rm").arg("-rf").arg("--")
+            .arg(format!("{}/{}", trash.root, FILES_DIR))
+            .arg(format!("{}/{}", trash.root, INFO_DIR)),
+    };
+
+    run_with_sudo_fallback(&sess, &connection_info, &command, Msg::SudoEmptyTrashFailed.text())?;
+
+    match ids {
+        Some(ids) => Ok(Msg::TrashEntriesDeleted.format(&[&ids.len()])),
+        None => Ok(Msg::TrashEmptied.text().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_trash_contents_out_of_trash() {
+        let trash = Trash {
+            root: "/home/user/.trash".to_string(),
+        };
+
+        assert!(trash.contains("/home/user/.trash"));
+        assert!(trash.contains("/home/user/.trash/files/1-a.txt"));
+        assert!(!trash.contains("/home/user/.trash-old/a.txt"));
+        assert_eq!(trash.info_path("1-a.txt"), "/home/user/.trash/info/1-a.txt.json");
+    }
+
+    #[test]
+    fn ids_are_plain_names() {
+        assert!(entry_id("/etc/nginx/").ends_with("-nginx"));
+        assert!(validate_id("1700000000-nginx").is_ok());
+        assert!(validate_id("../info").is_err());
+        assert!(validate_id("..").is_err());
+    }
+}
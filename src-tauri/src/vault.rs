@@ -0,0 +1,298 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{command, State};
+
+use crate::error::{AppError, ErrorCode};
+use crate::i18n::Msg;
+use crate::storage::{
+    get_config_dir, recover_servers_backup, reencrypt_servers, reencrypt_servers_with_commit, servers_have_encrypted_secrets,
+};
+
+const SECRET_PREFIX: &str = "vault:v1:";
+const VERIFIER_PLAINTEXT: &str = "ssh-connect-vault";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+// Параметры Argon2id по рекомендации OWASP; сохраняются в vault.json, чтобы их можно было менять
+const DEFAULT_M_COST: u32 = 19_456;
+const DEFAULT_T_COST: u32 = 2;
+const DEFAULT_P_COST: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct KdfParams {
+    algorithm: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+}
+
+/// Содержимое vault.json: параметры KDF и зашифрованная контрольная строка для проверки мастер-пароля.
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    verifier: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultStatus {
+    pub initialized: bool,
+    pub unlocked: bool,
+}
+
+/// Ключ шифрования, выведенный из мастер-пароля.
+pub struct VaultKey {
+    cipher: ChaCha20Poly1305,
+}
+
+impl VaultKey {
+    fn derive(passphrase: &str, kdf: &KdfParams) -> Result<Self, AppError> {
+        let salt = STANDARD.decode(&kdf.salt)
+            .map_err(|e| AppError::other(Msg::VaultSaltCorrupted.text(), e))?;
+
+        let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(KEY_LEN))
+            .map_err(|e| AppError::other(Msg::InvalidKdfParams.text(), e))?;
+
+        let mut key = [0u8; KEY_LEN];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| AppError::other(Msg::KeyDerivationFailed.text(), e))?;
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        key.fill(0);
+
+        Ok(VaultKey { cipher })
+    }
+
+    /// Шифрует секрет. Пустые строки и уже зашифрованные значения не меняются.
+    pub fn encrypt(&self, value: &str) -> Result<String, AppError> {
+        if value.is_empty() || is_encrypted(value) {
+            return Ok(value.to_string());
+        }
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, value.as_bytes())
+            .map_err(|_| AppError::internal(Msg::SecretEncryptFailed.text()))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+
+        Ok(format!("{}{}", SECRET_PREFIX, STANDARD.encode(payload)))
+    }
+
+    /// Расшифровывает секрет. Значения без префикса хранилища считаются открытым текстом.
+    pub fn decrypt(&self, value: &str) -> Result<String, AppError> {
+        let Some(encoded) = value.strip_prefix(SECRET_PREFIX) else {
+            return Ok(value.to_string());
+        };
+
+        let payload = STANDARD.decode(encoded)
+            .map_err(|e| AppError::other(Msg::SecretCorrupted.text(), e))?;
+
+        if payload.len() < NONCE_LEN {
+            return Err(AppError::internal(Msg::SecretCorrupted.text()));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::new(ErrorCode::VaultLocked, Msg::SecretDecryptFailed.text()))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|_| AppError::internal(Msg::SecretNotUtf8.text()))
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(SECRET_PREFIX)
+}
+
+/// Состояние хранилища учетных данных (хранится в состоянии Tauri).
+#[derive(Default)]
+pub struct Vault {
+    key: Mutex<Option<VaultKey>>,
+}
+
+impl Vault {
+    pub fn is_unlocked(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Выполняет `f` с ключом хранилища или возвращает ошибку, если оно заблокировано.
+    pub fn with_key<T>(&self, f: impl FnOnce(&VaultKey) -> Result<T, AppError>) -> Result<T, AppError> {
+        let key = self.lock();
+        let key = key.as_ref()
+            .ok_or_else(|| AppError::new(ErrorCode::VaultLocked, Msg::VaultIsLocked.text()))?;
+        f(key)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<VaultKey>> {
+        self.key.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn get_vault_file_path() -> Result<PathBuf, AppError> {
+    let mut config_dir = get_config_dir()?;
+    config_dir.push("vault.json");
+    Ok(config_dir)
+}
+
+/// Хранилище считается инициализированным, если создан vault.json (после первой разблокировки).
+pub fn vault_initialized() -> Result<bool, AppError> {
+    Ok(get_vault_file_path()?.exists())
+}
+
+fn read_vault_file() -> Result<VaultFile, AppError> {
+    let vault_path = get_vault_file_path()?;
+
+    let json_data = fs::read_to_string(&vault_path)
+        .map_err(|e| AppError::io(Msg::VaultReadFailed.text(), &e))?;
+
+    serde_json::from_str(&json_data)
+        .map_err(|e| AppError::other(Msg::VaultParseFailed.text(), e))
+}
+
+fn write_vault_file(vault_file: &VaultFile) -> Result<(), AppError> {
+    let vault_path = get_vault_file_path()?;
+    let temp_path = vault_path.with_extension("json.tmp");
+
+    let json_data = serde_json::to_string_pretty(vault_file)
+        .map_err(|e| AppError::other(Msg::VaultSerializeFailed.text(), e))?;
+
+    fs::write(&temp_path, json_data)
+        .map_err(|e| AppError::io(Msg::VaultWriteFailed.text(), &e))?;
+
+    fs::rename(&temp_path, &vault_path)
+        .map_err(|e| AppError::io(Msg::VaultWriteFailed.text(), &e))
+}
+
+fn new_kdf_params() -> KdfParams {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    KdfParams {
+        algorithm: "argon2id".to_string(),
+        m_cost: DEFAULT_M_COST,
+        t_cost: DEFAULT_T_COST,
+        p_cost: DEFAULT_P_COST,
+        salt: STANDARD.encode(salt),
+    }
+}
+
+fn create_vault_file(passphrase: &str) -> Result<(VaultFile, VaultKey), AppError> {
+    let kdf = new_kdf_params();
+    let key = VaultKey::derive(passphrase, &kdf)?;
+    let verifier = key.encrypt(VERIFIER_PLAINTEXT)?;
+
+    Ok((VaultFile { version: 1, kdf, verifier }, key))
+}
+
+fn open_vault_file(passphrase: &str) -> Result<VaultKey, AppError> {
+    let vault_file = read_vault_file()?;
+    let key = VaultKey::derive(passphrase, &vault_file.kdf)?;
+
+    match key.decrypt(&vault_file.verifier) {
+        Ok(verifier) if verifier == VERIFIER_PLAINTEXT => Ok(key),
+        _ => Err(AppError::new(ErrorCode::VaultLocked, Msg::WrongMasterPassword.text())),
+    }
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), AppError> {
+    if passphrase.is_empty() {
+        return Err(AppError::invalid_input(Msg::EmptyMasterPassword.text()));
+    }
+    Ok(())
+}
+
+#[command]
+pub fn get_vault_status(vault: State<'_, Vault>) -> Result<VaultStatus, AppError> {
+    Ok(VaultStatus {
+        initialized: vault_initialized()?,
+        unlocked: vault.is_unlocked(),
+    })
+}
+
+/// Разблокирует хранилище. При первом вызове создает его и шифрует
+/// пароли, которые до этого хранились в servers.json открытым текстом.
+#[command]
+pub fn unlock_vault(vault: State<'_, Vault>, passphrase: String) -> Result<String, AppError> {
+    check_passphrase(&passphrase)?;
+
+    if !vault_initialized()? {
+        return create_vault(&vault, &passphrase);
+    }
+
+    let key = open_vault_file(&passphrase)?;
+    // Смена мастер-пароля могла прерваться между записью servers.json и vault.json
+    recover_servers_backup(|value| key.decrypt(value).is_ok())?;
+
+    // Дошифровываем секреты, оставшиеся в открытом виде после ручной правки файла
+    reencrypt_servers(|value| key.decrypt(value), |value| key.encrypt(value))?;
+
+    *vault.lock() = Some(key);
+
+    Ok(Msg::VaultUnlocked.text().to_string())
+}
+
+/// Создает хранилище и шифрует пароли, которые хранились в servers.json открытым текстом.
+/// vault.json записывается последним, поэтому при сбое хранилище остается несозданным.
+fn create_vault(vault: &Vault, passphrase: &str) -> Result<String, AppError> {
+    // Создание могло прерваться после записи servers.json: тогда возвращается открытая копия
+    recover_servers_backup(|_| false)?;
+
+    // Без прежнего vault.json такие секреты не расшифровать, а новая соль сделает это невозможным навсегда
+    if servers_have_encrypted_secrets()? {
+        return Err(AppError::conflict(Msg::VaultFileMissing.text()));
+    }
+
+    let (vault_file, key) = create_vault_file(passphrase)?;
+
+    let migrated = reencrypt_servers_with_commit(
+        |value| key.decrypt(value),
+        |value| key.encrypt(value),
+        || write_vault_file(&vault_file),
+    )?;
+
+    *vault.lock() = Some(key);
+
+    Ok(Msg::VaultCreated.format(&[&migrated]))
+}
+
+#[command]
+pub fn lock_vault(vault: State<'_, Vault>) -> Result<String, AppError> {
+    *vault.lock() = None;
+    Ok(Msg::VaultLocked.text().to_string())
+}
+
+/// Меняет мастер-пароль: секреты расшифровываются старым ключом и шифруются новым с новой солью.
+#[command]
+pub fn rekey_vault(vault: State<'_, Vault>, current_passphrase: String, new_passphrase: String) -> Result<String, AppError> {
+    check_passphrase(&new_passphrase)?;
+
+    if !vault_initialized()? {
+        return Err(AppError::not_found(Msg::VaultNotCreated.text()));
+    }
+
+    let old_key = open_vault_file(&current_passphrase)?;
+    let (vault_file, new_key) = create_vault_file(&new_passphrase)?;
+
+    // vault.json с новым ключом записывается последним; если это не удалось, servers.json возвращается к старому ключу
+    reencrypt_servers_with_commit(
+        |value| old_key.decrypt(value),
+        |value| new_key.encrypt(value),
+        || write_vault_file(&vault_file),
+    )?;
+
+    *vault.lock() = Some(new_key);
+
+    Ok(Msg::MasterPasswordChanged.text().to_string())
+}